@@ -68,6 +68,18 @@ impl Assets {
 pub struct ItemAssets {
     /// Map from item name to its asset.
     pub assets: HashMap<Rc<str>, ItemAsset>,
+    /// Rune modules shared by all item scripts.
+    pub library: ScriptLibrary,
+    /// Problems found while loading the items: broken directories and missing files.
+    pub problems: Vec<ItemDiagnostic>,
+}
+
+/// Rune modules from the `_lib` directory, listed in its `_list.ron`.
+/// Every module is available to item scripts as `lib::<name>`.
+#[derive(Debug, Clone, Default)]
+pub struct ScriptLibrary {
+    /// Names of the modules (file names without `.rn`) and their sources.
+    pub modules: Vec<(String, String)>,
 }
 
 #[derive(Clone)]
@@ -83,9 +95,87 @@ pub struct ItemAsset {
 pub struct ItemConfig {
     pub name: Rc<str>,
     pub categories: Rc<[Category]>,
+    /// Free-form tags for the scripts to filter by, e.g. `["fire"]`.
+    #[serde(default)]
+    pub tags: Vec<Rc<str>>,
     pub appears_in_shop: ShopAppearance,
     #[serde(default)]
     pub base_stats: ItemStats,
+    #[serde(default)]
+    pub memory: MemoryConfig,
+    #[serde(default)]
+    pub shop: ShopConfig,
+    /// Added to the base stats for every tier above the first,
+    /// overrides the `tier_bonus` of the [MergeConfig].
+    #[serde(default)]
+    pub tier_bonus: Option<ItemStats>,
+    /// The item is never moved by the shift.
+    #[serde(default)]
+    pub anchored: bool,
+}
+
+/// How often and when the item is offered in the shop
+/// (if [ShopAppearance] allows it at all).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ShopConfig {
+    /// The chance to be offered relative to the other items, 1 is the usual.
+    pub weight: f32,
+    /// The first level the item can be offered on.
+    pub min_level: usize,
+    /// The last level the item can be offered on.
+    pub max_level: Option<usize>,
+    /// Names of the items the player has to own for this one to be offered.
+    pub requires: Vec<Rc<str>>,
+    /// The price in gold, the item is sold for half of it.
+    pub price: Gold,
+}
+
+/// What happens to the memory of the item script.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+    /// Whether duplicates start with a copy of the memory.
+    pub duplicate: bool,
+    /// Whether the memory is kept when the item turns into another one.
+    pub transform: bool,
+    /// The value to show as a counter on the item.
+    pub counter: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct EnemyAssets {
+    /// Map from enemy name to its asset.
+    pub assets: HashMap<Rc<str>, EnemyAsset>,
+    /// Problems found while loading the enemies.
+    pub problems: Vec<ItemDiagnostic>,
+}
+
+#[derive(Clone)]
+pub struct EnemyAsset {
+    pub config: EnemyConfig,
+    pub script: Option<String>,
+    pub texture: Option<Rc<ugli::Texture>>,
+}
+
+#[derive(geng::asset::Load, Debug, Clone, Serialize, Deserialize)]
+#[load(serde = "ron")]
+pub struct EnemyConfig {
+    pub name: Rc<str>,
+    /// Health on the first levels.
+    pub health: Hp,
+    /// Health is multiplied by this every 4 levels.
+    #[serde(default = "default_health_scaling")]
+    pub health_scaling: Hp,
+    #[serde(default)]
+    pub categories: Rc<[EnemyCategory]>,
+    /// The first level the enemy can appear on.
+    #[serde(default)]
+    pub min_level: usize,
+}
+
+fn default_health_scaling() -> Hp {
+    2
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -95,6 +185,39 @@ pub enum ShopAppearance {
     Never,
 }
 
+impl ShopConfig {
+    pub fn sell_price(&self) -> Gold {
+        self.price / 2
+    }
+
+    /// Whether the item can be offered on the level.
+    pub fn offered_on(&self, level: usize) -> bool {
+        self.min_level <= level && self.max_level.map_or(true, |max| level <= max)
+    }
+}
+
+impl Default for ShopConfig {
+    fn default() -> Self {
+        Self {
+            weight: 1.0,
+            min_level: 0,
+            max_level: None,
+            requires: Vec::new(),
+            price: 2,
+        }
+    }
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            duplicate: true,
+            transform: true,
+            counter: None,
+        }
+    }
+}
+
 impl ShopAppearance {
     pub fn check(self, map_full: bool) -> bool {
         match self {
@@ -106,14 +229,205 @@ impl ShopAppearance {
 }
 
 impl ItemAssets {
-    pub fn get(&self, item: &str) -> &ItemAsset {
-        self.assets
-            .get(item)
-            .unwrap_or_else(|| panic!("no assets found for item {}", item))
+    pub fn get(&self, item: &str) -> Option<&ItemAsset> {
+        self.assets.get(item)
     }
 
     pub fn get_texture(&self, item: &str) -> Option<&ugli::Texture> {
-        self.get(item).texture.as_deref()
+        self.get(item)?.texture.as_deref()
+    }
+}
+
+impl EnemyAssets {
+    pub fn get(&self, enemy: &str) -> Option<&EnemyAsset> {
+        self.assets.get(enemy)
+    }
+
+    pub fn get_texture(&self, enemy: &str) -> Option<&ugli::Texture> {
+        self.get(enemy)?.texture.as_deref()
+    }
+
+    /// Load the enemies synchronously without the textures.
+    /// Does not require the [Geng] context, so it can be used in the headless mode.
+    pub fn load_headless(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let list_path = path.join("_list.ron");
+        let list = std::fs::read_to_string(&list_path)
+            .context(format!("when loading enemy list at {:?}", list_path))?;
+        let list: Vec<String> =
+            ron::from_str(&list).context(format!("when parsing enemy list at {:?}", list_path))?;
+
+        let mut assets = HashMap::new();
+        let mut problems = Vec::new();
+        for name in list {
+            let enemy_path = path.join(&name);
+            match EnemyAsset::load_headless(&enemy_path) {
+                Ok(enemy) => {
+                    let has_texture = enemy_path.join("texture.png").exists();
+                    problems.extend(enemy.missing_texture(has_texture));
+                    assets.insert(Rc::clone(&enemy.config.name), enemy);
+                }
+                Err(err) => problems.push(EnemyAsset::load_failed(&name, err)),
+            }
+        }
+        Ok(Self { assets, problems })
+    }
+}
+
+impl EnemyAsset {
+    /// Warning about the missing texture, enemies may go without a script.
+    fn missing_texture(&self, has_texture: bool) -> Option<ItemDiagnostic> {
+        (!has_texture).then(|| {
+            ItemDiagnostic::warning(
+                Rc::clone(&self.config.name),
+                format!("{:?} is missing", "texture.png"),
+            )
+        })
+    }
+
+    /// The error for the `_list.ron` entry, whose directory failed to load.
+    fn load_failed(entry: &str, error: anyhow::Error) -> ItemDiagnostic {
+        ItemDiagnostic::error(
+            format!("enemies/_list.ron/{}", entry),
+            format!("failed to load the enemy: {:?}", error),
+        )
+    }
+
+    /// Load the enemy synchronously without the texture.
+    pub fn load_headless(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let config =
+            std::fs::read_to_string(path.join("config.ron")).context("'config.ron' expected")?;
+        let config = ron::from_str(&config).context("when parsing 'config.ron'")?;
+        Ok(Self {
+            config,
+            script: std::fs::read_to_string(path.join("script.rn")).ok(),
+            texture: None,
+        })
+    }
+}
+
+impl ItemAssets {
+    /// Load the items synchronously without the textures.
+    /// Does not require the [Geng] context, so it can be used in the headless mode.
+    pub fn load_headless(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let list_path = path.join("_list.ron");
+        let list = std::fs::read_to_string(&list_path)
+            .context(format!("when loading item list at {:?}", list_path))?;
+        let list: Vec<String> =
+            ron::from_str(&list).context(format!("when parsing item list at {:?}", list_path))?;
+
+        let mut assets = HashMap::new();
+        let mut problems = Vec::new();
+        for name in list {
+            let item_path = path.join(&name);
+            match ItemAsset::load_headless(&item_path) {
+                Ok(item) => {
+                    let has_texture = item_path.join("texture.png").exists();
+                    problems.extend(item.missing_files(has_texture));
+                    assets.insert(Rc::clone(&item.config.name), item);
+                }
+                Err(err) => problems.push(ItemAsset::load_failed(&name, err)),
+            }
+        }
+        let library = ScriptLibrary::load_headless(path.join("_lib"), &mut problems);
+        Ok(Self {
+            assets,
+            library,
+            problems,
+        })
+    }
+}
+
+impl ScriptLibrary {
+    /// The error for the `_list.ron` entry, whose module failed to load.
+    fn load_failed(entry: &str, error: anyhow::Error) -> ItemDiagnostic {
+        ItemDiagnostic::error(
+            format!("_lib/_list.ron/{}", entry),
+            format!("failed to load the module: {:?}", error),
+        )
+    }
+
+    /// Load the modules synchronously. The library is optional,
+    /// so it is empty if there is no list.
+    pub fn load_headless(
+        path: impl AsRef<std::path::Path>,
+        problems: &mut Vec<ItemDiagnostic>,
+    ) -> Self {
+        let path = path.as_ref();
+        let Ok(list) = std::fs::read_to_string(path.join("_list.ron")) else {
+            return Self::default();
+        };
+        let list: Vec<String> = match ron::from_str(&list) {
+            Ok(list) => list,
+            Err(err) => {
+                problems.push(ItemDiagnostic::error(
+                    "_lib/_list.ron",
+                    format!("failed to parse the module list: {}", err),
+                ));
+                return Self::default();
+            }
+        };
+
+        let mut modules = Vec::new();
+        for name in list {
+            let file = path.join(format!("{}.rn", name));
+            match std::fs::read_to_string(&file) {
+                Ok(source) => modules.push((name, source)),
+                Err(err) => problems.push(Self::load_failed(&name, err.into())),
+            }
+        }
+        Self { modules }
+    }
+}
+
+impl ItemAsset {
+    /// Whether the script calls into the [ScriptLibrary].
+    pub fn uses_library(&self) -> bool {
+        self.script
+            .as_deref()
+            .map_or(false, |script| script.contains("lib::"))
+    }
+
+    /// Warnings about the optional files of the item that are missing.
+    fn missing_files(&self, has_texture: bool) -> Vec<ItemDiagnostic> {
+        [
+            ("description.txt", self.description.is_some()),
+            ("script.rn", self.script.is_some()),
+            ("texture.png", has_texture),
+        ]
+        .into_iter()
+        .filter(|(_, present)| !present)
+        .map(|(file, _)| {
+            ItemDiagnostic::warning(
+                Rc::clone(&self.config.name),
+                format!("{:?} is missing", file),
+            )
+        })
+        .collect()
+    }
+
+    /// The error for the `_list.ron` entry, whose directory failed to load.
+    fn load_failed(entry: &str, error: anyhow::Error) -> ItemDiagnostic {
+        ItemDiagnostic::error(
+            format!("_list.ron/{}", entry),
+            format!("failed to load the item: {:?}", error),
+        )
+    }
+
+    /// Load the item synchronously without the texture.
+    pub fn load_headless(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let config =
+            std::fs::read_to_string(path.join("config.ron")).context("'config.ron' expected")?;
+        let config = ron::from_str(&config).context("when parsing 'config.ron'")?;
+        Ok(Self {
+            config,
+            description: std::fs::read_to_string(path.join("description.txt")).ok(),
+            script: std::fs::read_to_string(path.join("script.rn")).ok(),
+            texture: None,
+        })
     }
 }
 
@@ -133,17 +447,34 @@ impl geng::asset::Load for ItemAssets {
                 let manager = &manager;
                 let path = &path;
                 async move {
-                    let path = path.join(name);
-                    let item: ItemAsset = geng::asset::Load::load(manager, &path, &()).await?;
-                    anyhow::Ok((Rc::clone(&item.config.name), item))
+                    let item: anyhow::Result<ItemAsset> =
+                        geng::asset::Load::load(manager, &path.join(&name), &()).await;
+                    (name, item)
                 }
             });
-            let items = future::join_all(item_loaders)
-                .await
-                .into_iter()
-                .flatten()
-                .collect();
-            Ok(Self { assets: items })
+
+            let mut assets = HashMap::new();
+            let mut problems = Vec::new();
+            for (name, item) in future::join_all(item_loaders).await {
+                match item {
+                    Ok(item) => {
+                        problems.extend(item.missing_files(item.texture.is_some()));
+                        assets.insert(Rc::clone(&item.config.name), item);
+                    }
+                    Err(err) => {
+                        let problem = ItemAsset::load_failed(&name, err);
+                        log::error!("{}", problem);
+                        problems.push(problem);
+                    }
+                }
+            }
+
+            let library = ScriptLibrary::load(&path.join("_lib"), &mut problems).await;
+            Ok(Self {
+                assets,
+                library,
+                problems,
+            })
         }
         .boxed_local()
     }
@@ -151,6 +482,40 @@ impl geng::asset::Load for ItemAssets {
     const DEFAULT_EXT: Option<&'static str> = None;
 }
 
+impl ScriptLibrary {
+    /// Load the modules asynchronously, same as [ScriptLibrary::load_headless].
+    async fn load(path: &std::path::Path, problems: &mut Vec<ItemDiagnostic>) -> Self {
+        let Ok(list) = file::load_string(path.join("_list.ron")).await else {
+            return Self::default();
+        };
+        let list: Vec<String> = match ron::from_str(&list) {
+            Ok(list) => list,
+            Err(err) => {
+                let problem = ItemDiagnostic::error(
+                    "_lib/_list.ron",
+                    format!("failed to parse the module list: {}", err),
+                );
+                log::error!("{}", problem);
+                problems.push(problem);
+                return Self::default();
+            }
+        };
+
+        let mut modules = Vec::new();
+        for name in list {
+            match file::load_string(path.join(format!("{}.rn", name))).await {
+                Ok(source) => modules.push((name, source)),
+                Err(err) => {
+                    let problem = Self::load_failed(&name, err);
+                    log::error!("{}", problem);
+                    problems.push(problem);
+                }
+            }
+        }
+        Self { modules }
+    }
+}
+
 impl geng::asset::Load for ItemAsset {
     type Options = ();
 
@@ -186,3 +551,81 @@ impl geng::asset::Load for ItemAsset {
 
     const DEFAULT_EXT: Option<&'static str> = None;
 }
+
+impl geng::asset::Load for EnemyAssets {
+    type Options = ();
+
+    fn load(
+        manager: &geng::asset::Manager,
+        path: &std::path::Path,
+        &(): &Self::Options,
+    ) -> geng::asset::Future<Self> {
+        let manager = manager.clone();
+        let path = path.to_owned();
+        async move {
+            let list: Vec<String> = file::load_detect(path.join("_list.ron")).await?;
+            let enemy_loaders = list.into_iter().map(|name| {
+                let manager = &manager;
+                let path = &path;
+                async move {
+                    let enemy: anyhow::Result<EnemyAsset> =
+                        geng::asset::Load::load(manager, &path.join(&name), &()).await;
+                    (name, enemy)
+                }
+            });
+
+            let mut assets = HashMap::new();
+            let mut problems = Vec::new();
+            for (name, enemy) in future::join_all(enemy_loaders).await {
+                match enemy {
+                    Ok(enemy) => {
+                        problems.extend(enemy.missing_texture(enemy.texture.is_some()));
+                        assets.insert(Rc::clone(&enemy.config.name), enemy);
+                    }
+                    Err(err) => {
+                        let problem = EnemyAsset::load_failed(&name, err);
+                        log::error!("{}", problem);
+                        problems.push(problem);
+                    }
+                }
+            }
+            Ok(Self { assets, problems })
+        }
+        .boxed_local()
+    }
+
+    const DEFAULT_EXT: Option<&'static str> = None;
+}
+
+impl geng::asset::Load for EnemyAsset {
+    type Options = ();
+
+    fn load(
+        manager: &geng::asset::Manager,
+        path: &std::path::Path,
+        (): &Self::Options,
+    ) -> geng::asset::Future<Self> {
+        let manager = manager.clone();
+        let path = path.to_owned();
+        async move {
+            Ok(Self {
+                config: geng::asset::Load::load(&manager, &path.join("config.ron"), &())
+                    .await
+                    .context("'config.ron' expected")?,
+                script: geng::asset::Load::load(&manager, &path.join("script.rn"), &())
+                    .await
+                    .ok(),
+                texture: geng::asset::Load::load(
+                    &manager,
+                    &path.join("texture.png"),
+                    &geng::asset::TextureOptions::default(),
+                )
+                .await
+                .ok(),
+            })
+        }
+        .boxed_local()
+    }
+
+    const DEFAULT_EXT: Option<&'static str> = None;
+}
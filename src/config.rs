@@ -8,6 +8,96 @@ pub struct Config {
     pub score_per_level: Score,
     pub score_per_turn_left: Score,
     pub starting_items: Vec<Box<str>>,
+    /// Whether a script compilation error should prevent the game from starting.
+    /// Otherwise, the broken scripts are replaced with empty ones.
+    #[serde(default)]
+    pub strict_scripts: bool,
+    /// The maximum number of instructions a single script call can execute.
+    #[serde(default = "default_script_budget")]
+    pub script_budget: usize,
+    /// How deep items can trigger each other (e.g. by using one another)
+    /// before the chain is cut off.
+    #[serde(default = "default_max_effect_depth")]
+    pub max_effect_depth: usize,
+    /// Let the player place the items at the start of every level,
+    /// instead of scattering them randomly.
+    #[serde(default)]
+    pub manual_placement: bool,
+    /// The most items the player can keep, the extra ones have to be discarded.
+    #[serde(default = "default_inventory_capacity")]
+    pub inventory_capacity: usize,
+    #[serde(default)]
+    pub economy: EconomyConfig,
+    #[serde(default)]
+    pub merge: MergeConfig,
+}
+
+/// Where the gold comes from and what it is spent on.
+/// Item prices are set in their configs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EconomyConfig {
+    pub starting_gold: Gold,
+    /// For every enemy killed.
+    pub gold_per_kill: Gold,
+    /// For every Treasure item on the board when the level is finished.
+    pub gold_per_treasure: Gold,
+    /// For every turn left when the level is finished.
+    pub gold_per_turn_left: Gold,
+    /// The price of new options in the shop.
+    pub reroll_price: Gold,
+}
+
+/// How copies of an item fuse into a higher tier.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MergeConfig {
+    /// Number of copies of the same tier needed to merge.
+    pub copies: usize,
+    pub max_tier: usize,
+    /// Merge at the start of the shop phase without asking the player.
+    pub auto: bool,
+    /// Added to the base stats for every tier above the first,
+    /// only to the stats the item already has.
+    pub tier_bonus: ItemStats,
+}
+
+impl Default for MergeConfig {
+    fn default() -> Self {
+        Self {
+            copies: 3,
+            max_tier: 3,
+            auto: true,
+            tier_bonus: ItemStats {
+                damage: Some(1),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Default for EconomyConfig {
+    fn default() -> Self {
+        Self {
+            starting_gold: 5,
+            gold_per_kill: 2,
+            gold_per_treasure: 1,
+            gold_per_turn_left: 1,
+            reroll_price: 1,
+        }
+    }
+}
+
+fn default_script_budget() -> usize {
+    1_000_000
+}
+
+fn default_max_effect_depth() -> usize {
+    32
+}
+
+fn default_inventory_capacity() -> usize {
+    12
 }
 
 impl Config {
@@ -18,4 +108,12 @@ impl Config {
             .context(format!("when loading config file at {:?}", path))?;
         ron::from_str(&content).context(format!("when parsing config file at {:?}", path))
     }
+
+    /// Load the config synchronously, without the [Geng] context.
+    pub fn load_headless(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .context(format!("when loading config file at {:?}", path))?;
+        ron::from_str(&content).context(format!("when parsing config file at {:?}", path))
+    }
 }
@@ -1,12 +1,50 @@
 use geng::{Key, MouseButton};
 
-use crate::{controls::*, prelude::*, render::GameRender};
+use crate::{controls::*, prelude::*, render::GameRender, watcher::ItemWatcher};
+
+/// Options for a single run.
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Fixed seed for the run, random if not specified.
+    pub seed: Option<u64>,
+    /// Path to keep the recording of the run at.
+    /// The runs started with a retry are recorded next to it.
+    pub record: Option<std::path::PathBuf>,
+    /// Replay to play back instead of listening to the player.
+    pub replay: Option<Replay>,
+    /// Saved run to continue.
+    pub resume: Option<SavedRun>,
+    /// Reload the items when their files change.
+    pub hot_reload: bool,
+    /// Place the items manually instead of randomly, overrides the config.
+    pub manual_placement: Option<bool>,
+}
+
+/// The key under which the unfinished run is kept in the preferences.
+const SAVED_RUN_KEY: &str = "quantum_dungeon_saved_run";
+
+/// Load the unfinished run, if there is one.
+pub fn load_saved_run() -> Option<SavedRun> {
+    preferences::load::<Option<SavedRun>>(SAVED_RUN_KEY).flatten()
+}
+
+fn save_run(saved: Option<&SavedRun>) {
+    preferences::save(SAVED_RUN_KEY, &saved);
+}
 
 pub struct Game {
     // geng: Geng,
     assets: Rc<Assets>,
     render: GameRender,
     model: Model,
+    /// Time accumulated for the next fixed model update.
+    update_time: f32,
+    record_path: Option<std::path::PathBuf>,
+    /// The number of retries, each run is recorded to a separate file.
+    retries: usize,
+    playback: Option<ReplayPlayback>,
+    /// Watches the item files, when hot reloading is enabled.
+    watcher: Option<ItemWatcher>,
     framebuffer_size: vec2<usize>,
 
     cursor_pos: vec2<f64>,
@@ -24,12 +62,50 @@ impl Game {
         assets: &Rc<Assets>,
         config: Config,
         all_items: &Rc<ItemAssets>,
-    ) -> Self {
-        Self {
+        all_enemies: &Rc<EnemyAssets>,
+        options: RunOptions,
+    ) -> anyhow::Result<Self> {
+        let (config, seed) = match &options.replay {
+            Some(replay) => (replay.config.clone(), replay.seed),
+            None => {
+                let mut config = config;
+                if let Some(manual) = options.manual_placement {
+                    config.manual_placement = manual;
+                }
+                (config, options.seed.unwrap_or_else(|| thread_rng().gen()))
+            }
+        };
+        let mut record_path = options.record;
+        let model = match options.resume {
+            Some(saved) if options.replay.is_none() => {
+                if record_path.take().is_some() {
+                    log::warn!("Recording a continued run is not supported");
+                }
+                Model::load(all_items.clone(), all_enemies.clone(), saved).or_else(|err| {
+                    log::error!("Failed to continue the saved run: {:?}", err);
+                    Model::new(config, all_items.clone(), all_enemies.clone(), seed)
+                })?
+            }
+            _ => Model::new(config, all_items.clone(), all_enemies.clone(), seed)?,
+        };
+        let playback = options.replay.map(|replay| {
+            replay.check_items(&model);
+            ReplayPlayback::new(replay)
+        });
+        let watcher = options
+            .hot_reload
+            .then(|| ItemWatcher::new(run_dir().join("assets").join("items")));
+
+        Ok(Self {
             // geng: geng.clone(),
             assets: assets.clone(),
-            render: GameRender::new(geng, assets, all_items),
-            model: Model::new(assets.clone(), config, all_items.clone()),
+            render: GameRender::new(geng, assets, all_items, all_enemies),
+            model,
+            update_time: 0.0,
+            record_path,
+            retries: 0,
+            playback,
+            watcher,
             framebuffer_size: vec2(1, 1),
 
             cursor_pos: vec2::ZERO,
@@ -37,7 +113,7 @@ impl Game {
             cursor_world_pos: vec2::ZERO,
             cursor_grid_pos: vec2::ZERO,
             touch_controller: TouchController::new(),
-        }
+        })
     }
 
     fn handle_lmb(&mut self) {
@@ -50,13 +126,43 @@ impl Game {
         match self.model.phase {
             Phase::GameOver => {
                 if self.render.retry_button.contains(self.cursor_ui_pos) {
-                    self.model.player_action(PlayerInput::Retry);
+                    self.player_action(PlayerInput::Retry);
                 }
             }
-            Phase::Player | Phase::Map { .. } | Phase::Portal { .. }
+            Phase::Player | Phase::Map { .. } | Phase::Portal { .. } | Phase::Placement { .. }
                 if self.render.skip_turn_button.contains(self.cursor_ui_pos) =>
             {
-                self.model.player_action(PlayerInput::Skip);
+                self.render.dragged_item = None;
+                self.player_action(PlayerInput::Skip);
+            }
+            Phase::Placement { .. } => {
+                // Either a drag, or a tap to pick and another to drop
+                if !self.drop_dragged_item() {
+                    self.render.dragged_item = self.hovered_inventory_item().or_else(|| {
+                        let target = self.cursor_grid_pos.map(|x| x.floor() as Coord);
+                        let state = self.model.state.borrow();
+                        state
+                            .items
+                            .iter()
+                            .find(|(_, item)| item.position == target)
+                            .map(|(_, item)| item.item_id)
+                    });
+                }
+            }
+            Phase::Discard { .. } => {
+                if let Some(id) = self.hovered_inventory_item() {
+                    self.player_action(PlayerInput::DiscardItem(id));
+                }
+            }
+            Phase::Player if self.render.show_inventory => {
+                if let Some(id) = self.hovered_inventory_item() {
+                    self.player_action(PlayerInput::DiscardItem(id));
+                }
+            }
+            Phase::Select { .. } if self.render.show_inventory => {
+                if let Some(id) = self.hovered_inventory_item() {
+                    self.player_action(PlayerInput::SellItem(id));
+                }
             }
             Phase::Select { .. } => {
                 if let Some(i) = self
@@ -65,17 +171,17 @@ impl Game {
                     .iter()
                     .position(|(_, button)| button.contains(self.cursor_ui_pos))
                 {
-                    self.model.player_action(PlayerInput::SelectItem(i));
+                    self.player_action(PlayerInput::SelectItem(i));
                 } else if self.render.reroll_button.contains(self.cursor_ui_pos) {
-                    self.model.player_action(PlayerInput::Reroll);
+                    self.player_action(PlayerInput::Reroll);
                 } else if self.render.skip_item_button.contains(self.cursor_ui_pos) {
-                    self.model.player_action(PlayerInput::Skip);
+                    self.player_action(PlayerInput::Skip);
                 }
             }
             Phase::Vision => {
                 let target = self.cursor_grid_pos.map(|x| x.floor() as Coord);
                 // if self.model.grid.check_pos(target) {
-                self.model.player_action(PlayerInput::Vision {
+                self.player_action(PlayerInput::Vision {
                     pos: target,
                     commit: true,
                 });
@@ -83,10 +189,207 @@ impl Game {
             }
             _ => {
                 let target = self.cursor_grid_pos.map(|x| x.floor() as Coord);
-                self.model.player_action(PlayerInput::Tile(target));
+                self.player_action(PlayerInput::Tile(target));
+            }
+        }
+    }
+
+    /// Merge the clicked item in the inventory.
+    fn handle_rmb(&mut self) {
+        if !self.render.show_inventory || !matches!(self.model.phase, Phase::Select { .. }) {
+            return;
+        }
+        if let Some(id) = self.hovered_inventory_item() {
+            self.player_action(PlayerInput::MergeItem(id));
+        }
+    }
+
+    /// Place the dragged item on the tile under the cursor.
+    /// Returns `false` if nothing is dragged or the tile is not free.
+    fn drop_dragged_item(&mut self) -> bool {
+        let Some(item) = self.render.dragged_item else {
+            return false;
+        };
+        let position = self.cursor_grid_pos.map(|x| x.floor() as Coord);
+        let state = self.model.state.borrow();
+        let free = state.grid.check_pos(position)
+            && !state
+                .entities
+                .iter()
+                .any(|(_, entity)| entity.position == position)
+            && !state
+                .items
+                .iter()
+                .any(|(_, item)| item.position == position);
+        drop(state);
+        if !free {
+            return false;
+        }
+
+        self.render.dragged_item = None;
+        self.player_action(PlayerInput::PlaceItem { item, position });
+        true
+    }
+
+    /// The item in the inventory panel under the cursor.
+    fn hovered_inventory_item(&self) -> Option<Id> {
+        self.render
+            .inventory_slots
+            .iter()
+            .find(|(_, slot)| slot.contains(self.cursor_ui_pos))
+            .map(|&(id, _)| id)
+    }
+
+    fn player_action(&mut self, input: PlayerInput) {
+        if self.playback.is_some() {
+            // The inputs come from the replay
+            return;
+        }
+        let retry =
+            matches!(input, PlayerInput::Retry) && matches!(self.model.phase, Phase::GameOver);
+        self.model.player_action(input);
+        if retry && !matches!(self.model.phase, Phase::GameOver) {
+            // A new run has started
+            self.retries += 1;
+        }
+    }
+
+    /// The file to record the current run to.
+    fn record_file(&self) -> Option<std::path::PathBuf> {
+        let path = self.record_path.as_ref()?;
+        if self.retries == 0 {
+            return Some(path.clone());
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match path.extension() {
+            Some(ext) => format!("{}-{}.{}", stem, self.retries, ext.to_string_lossy()),
+            None => format!("{}-{}", stem, self.retries),
+        };
+        Some(path.with_file_name(name))
+    }
+
+    /// Save the recording of the current run, unless it is a playback.
+    fn save_replay(&self) {
+        if self.playback.is_some() {
+            return;
+        }
+        let Some(path) = self.record_file() else {
+            return;
+        };
+        if let Err(err) = self.model.replay.save(&path) {
+            log::error!("Failed to save the replay: {:?}", err);
+        }
+    }
+
+    /// Reload the items whose files have changed.
+    fn hot_reload(&mut self, delta_time: f32) {
+        let Some(watcher) = &mut self.watcher else {
+            return;
+        };
+        let library_path = watcher.library_path();
+        for dir in watcher.update(delta_time) {
+            if dir == library_path {
+                self.reload_library(&dir);
+                continue;
+            }
+
+            let mut asset = match ItemAsset::load_headless(&dir) {
+                Ok(asset) => asset,
+                Err(err) => {
+                    log::error!("Failed to reload the item at {:?}: {:?}", dir, err);
+                    self.render
+                        .show_toast(format!("Failed to reload {}: {}", dir.display(), err));
+                    continue;
+                }
+            };
+            // Textures are not reloaded
+            asset.texture = self
+                .model
+                .item_assets
+                .get(&asset.config.name)
+                .and_then(|old| old.texture.clone());
+
+            let name = Rc::clone(&asset.config.name);
+            match self.model.reload_item(asset) {
+                Ok(()) => {
+                    self.render.set_items(self.model.item_assets.clone());
+                    self.render.show_toast(format!("Reloaded {}", name));
+                }
+                Err(report) => {
+                    report.log();
+                    for diagnostic in &report.diagnostics {
+                        self.render.show_toast(diagnostic.to_string());
+                    }
+                }
             }
         }
     }
+
+    /// Reload the script library and the items that use it.
+    fn reload_library(&mut self, dir: &std::path::Path) {
+        let mut problems = Vec::new();
+        let library = ScriptLibrary::load_headless(dir, &mut problems);
+        let result = if problems.is_empty() {
+            self.model.reload_library(library)
+        } else {
+            Err(ItemReport {
+                diagnostics: problems,
+            })
+        };
+
+        match result {
+            Ok(names) => {
+                self.render.set_items(self.model.item_assets.clone());
+                self.render.show_toast("Reloaded the library");
+                for name in names {
+                    self.render.show_toast(format!("Reloaded {}", name));
+                }
+            }
+            Err(report) => {
+                report.log();
+                for diagnostic in &report.diagnostics {
+                    self.render.show_toast(diagnostic.to_string());
+                }
+            }
+        }
+    }
+
+    fn handle_model_events(&mut self) {
+        for event in std::mem::take(&mut self.model.events) {
+            match event {
+                ModelEvent::PlaySound(sound) => {
+                    let sound = match sound {
+                        SoundEvent::Step => &self.assets.sounds.step,
+                        SoundEvent::Damage => &self.assets.sounds.damage,
+                        SoundEvent::EnemyDeath => &self.assets.sounds.enemy_death,
+                    };
+                    sound.play();
+                }
+                ModelEvent::Checkpoint(saved) => {
+                    // Do not overwrite the actual run with the replayed one
+                    if self.playback.is_none() {
+                        save_run(Some(&saved));
+                    }
+                }
+                ModelEvent::GameOver => {
+                    if self.playback.is_none() {
+                        save_run(None);
+                    }
+                    self.save_replay();
+                }
+                ModelEvent::Error(error) => {
+                    self.render.show_toast(error.to_string());
+                }
+            }
+        }
+    }
+}
+
+impl Drop for Game {
+    fn drop(&mut self) {
+        // Keep the unfinished run
+        self.save_replay();
+    }
 }
 
 impl geng::State for Game {
@@ -144,26 +447,36 @@ impl geng::State for Game {
             vec2(0, 0)
         };
         if move_dir != vec2::ZERO {
-            self.model.player_action(PlayerInput::Dir(move_dir));
+            self.player_action(PlayerInput::Dir(move_dir));
             return;
         }
 
         if geng_utils::key::is_event_press(&event, [Key::Digit1]) {
-            self.model.player_action(PlayerInput::SelectItem(0));
+            self.player_action(PlayerInput::SelectItem(0));
         } else if geng_utils::key::is_event_press(&event, [Key::Digit2]) {
-            self.model.player_action(PlayerInput::SelectItem(1));
+            self.player_action(PlayerInput::SelectItem(1));
         } else if geng_utils::key::is_event_press(&event, [Key::Digit3]) {
-            self.model.player_action(PlayerInput::SelectItem(2));
+            self.player_action(PlayerInput::SelectItem(2));
         }
 
         if geng_utils::key::is_event_press(&event, [MouseButton::Left]) {
             self.handle_lmb();
+        } else if let geng::Event::MouseRelease {
+            button: MouseButton::Left,
+        } = event
+        {
+            if let Phase::Placement { .. } = self.model.phase {
+                self.drop_dragged_item();
+            }
+        } else if geng_utils::key::is_event_press(&event, [MouseButton::Right]) {
+            self.handle_rmb();
         }
     }
 
     fn update(&mut self, delta_time: f64) {
         self.touch_controller.update(delta_time);
-        let delta_time = Time::new(delta_time as _);
+        self.render.update(r32(delta_time as f32));
+        self.hot_reload(delta_time as f32);
 
         self.cursor_world_pos = self
             .render
@@ -175,7 +488,7 @@ impl geng::State for Game {
             .screen_to_world(self.framebuffer_size.as_f32(), self.cursor_pos.as_f32());
         self.cursor_grid_pos = self.cursor_world_pos / self.render.cell_size + vec2::splat(0.5);
 
-        if let Phase::Vision = self.model.phase {
+        if self.playback.is_none() && matches!(self.model.phase, Phase::Vision) {
             let target = self.cursor_grid_pos.map(|x| x.floor() as Coord);
             self.model.player_action(PlayerInput::Vision {
                 pos: target,
@@ -183,6 +496,18 @@ impl geng::State for Game {
             });
         }
 
-        self.model.update(delta_time);
+        // Update the model in fixed steps for the replays to be deterministic
+        // Skip the time lost in freezes
+        self.update_time = (self.update_time + delta_time as f32).min(FIXED_DELTA_TIME * 10.0);
+        while self.update_time >= FIXED_DELTA_TIME {
+            self.update_time -= FIXED_DELTA_TIME;
+            if let Some(playback) = &mut self.playback {
+                while let Some(input) = playback.next_input(self.model.ticks) {
+                    self.model.player_action(input);
+                }
+            }
+            self.model.update(r32(FIXED_DELTA_TIME));
+        }
+        self.handle_model_events();
     }
 }
@@ -0,0 +1,235 @@
+use crate::prelude::*;
+
+use std::path::Path;
+
+/// Runs the game logic without a window, rendering, or audio.
+pub struct Simulation {
+    pub model: Model,
+    /// The time step of every update.
+    pub delta_time: Time,
+    /// The number of updates performed so far.
+    pub steps: usize,
+}
+
+impl Simulation {
+    pub fn new(
+        config: Config,
+        items: Rc<ItemAssets>,
+        enemies: Rc<EnemyAssets>,
+        seed: u64,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            model: Model::new(config, items, enemies, seed)?,
+            delta_time: r32(FIXED_DELTA_TIME),
+            steps: 0,
+        })
+    }
+
+    /// Load the config, the items and the enemies (without textures) from the disk and start a new run.
+    pub fn load(config_path: &Path, assets_path: &Path, seed: u64) -> anyhow::Result<Self> {
+        let config = Config::load_headless(config_path)?;
+        let items = ItemAssets::load_headless(assets_path.join("items"))?;
+        let enemies = EnemyAssets::load_headless(assets_path.join("enemies"))?;
+        Self::new(config, Rc::new(items), Rc::new(enemies), seed)
+    }
+
+    /// Update the model by a single time step.
+    pub fn step(&mut self) {
+        self.model.update(self.delta_time);
+        // Nobody is there to listen
+        self.model.events.clear();
+        self.steps += 1;
+    }
+
+    pub fn input(&mut self, input: PlayerInput) {
+        self.model.player_action(input);
+    }
+
+    /// Keep updating the model until it expects an input from the player.
+    /// Returns `false` if the model is still busy after `max_steps` updates.
+    pub fn run_until_input(&mut self, max_steps: usize) -> bool {
+        for _ in 0..max_steps {
+            if self.model.is_waiting_for_input() {
+                return true;
+            }
+            self.step();
+        }
+        self.model.is_waiting_for_input()
+    }
+
+    /// Let the bot play until game over or until the step limit is reached.
+    /// Returns the number of inputs made.
+    pub fn play(&mut self, bot: &mut RandomBot, max_steps: usize) -> usize {
+        let mut inputs = 0;
+        while self.steps < max_steps {
+            if !self.run_until_input(max_steps - self.steps) {
+                break;
+            }
+            let Some(input) = bot.choose_input(&self.model) else {
+                break;
+            };
+            self.input(input);
+            inputs += 1;
+            self.step();
+        }
+        inputs
+    }
+
+    /// Feed the recorded inputs into the model at their ticks, then let it settle.
+    /// Returns `false` if the step limit is reached before all inputs have been applied.
+    pub fn play_back(&mut self, replay: Replay, max_steps: usize) -> bool {
+        self.delta_time = replay.delta_time;
+        let mut playback = ReplayPlayback::new(replay);
+        while !playback.is_finished() && self.steps < max_steps {
+            while let Some(input) = playback.next_input(self.model.ticks) {
+                self.input(input);
+            }
+            self.step();
+        }
+        self.run_until_input(max_steps.saturating_sub(self.steps));
+        playback.is_finished()
+    }
+}
+
+/// A player that makes random valid decisions.
+pub struct RandomBot {
+    rng: StdRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Choose the next input for the current phase.
+    /// Returns `None` when the game is over.
+    pub fn choose_input(&mut self, model: &Model) -> Option<PlayerInput> {
+        let state = model.state.borrow();
+        let player_pos = state
+            .entities
+            .iter()
+            .find(|(_, entity)| matches!(entity.kind, EntityKind::Player))
+            .map(|(_, entity)| entity.position);
+        let directions = [vec2(1, 0), vec2(-1, 0), vec2(0, 1), vec2(0, -1)];
+
+        let input = match &model.phase {
+            Phase::Player => {
+                let moves: Vec<_> = player_pos
+                    .into_iter()
+                    .flat_map(|pos| directions.map(|dir| (pos, dir)))
+                    .filter(|&(pos, dir)| {
+                        state.grid.check_pos(pos + dir)
+                            && !state.grid.fractured.contains(&(pos + dir))
+                    })
+                    .map(|(_, dir)| dir)
+                    .collect();
+                moves
+                    .choose(&mut self.rng)
+                    .map_or(PlayerInput::Skip, |&dir| PlayerInput::Dir(dir))
+            }
+            Phase::Vision => {
+                let pos = player_pos.unwrap_or(vec2::ZERO);
+                let dir = *directions.choose(&mut self.rng).unwrap();
+                PlayerInput::Vision {
+                    pos: pos + dir,
+                    commit: true,
+                }
+            }
+            Phase::Map { .. } => state
+                .grid
+                .outside_tiles()
+                .into_iter()
+                .sorted_by_key(|pos| (pos.x, pos.y))
+                .choose(&mut self.rng)
+                .map_or(PlayerInput::Skip, PlayerInput::Tile),
+            Phase::Select { options, .. } => {
+                let gold = state.player.gold;
+                let affordable: Vec<usize> = options
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, item)| item.config.shop.price <= gold)
+                    .map(|(i, _)| i)
+                    .collect();
+                affordable
+                    .choose(&mut self.rng)
+                    .map_or(PlayerInput::Skip, |&i| PlayerInput::SelectItem(i))
+            }
+            Phase::Discard { .. } => {
+                let items: Vec<Id> = state.player.items.iter().map(|(id, _)| id).collect();
+                items
+                    .choose(&mut self.rng)
+                    .map_or(PlayerInput::Skip, |&id| PlayerInput::DiscardItem(id))
+            }
+            Phase::GameOver => return None,
+            _ => PlayerInput::Skip,
+        };
+        Some(input)
+    }
+}
+
+/// Play a full game with random decisions until game over or until the step limit is reached.
+pub fn run(config_path: &Path, seed: Option<u64>, max_steps: usize) -> anyhow::Result<()> {
+    let seed = seed.unwrap_or_else(|| thread_rng().gen());
+    let mut simulation = Simulation::load(config_path, &run_dir().join("assets"), seed)?;
+    let inputs = simulation.play(&mut RandomBot::new(seed), max_steps);
+
+    let model = &simulation.model;
+    if let Phase::GameOver = model.phase {
+        log::info!("Game over");
+    } else {
+        log::warn!("Reached the step limit of {} before game over", max_steps);
+    }
+    println!(
+        "Seed {}: reached level {} with score {} ({} steps, {} inputs)",
+        seed, model.level, model.score, simulation.steps, inputs
+    );
+
+    Ok(())
+}
+
+/// Play back the recorded run until all inputs have been applied and the model settles.
+pub fn replay(replay: Replay, max_steps: usize) -> anyhow::Result<()> {
+    let items = ItemAssets::load_headless(run_dir().join("assets").join("items"))?;
+    let enemies = EnemyAssets::load_headless(run_dir().join("assets").join("enemies"))?;
+    let mut simulation = Simulation::new(
+        replay.config.clone(),
+        Rc::new(items),
+        Rc::new(enemies),
+        replay.seed,
+    )?;
+    replay.check_items(&simulation.model);
+
+    let seed = replay.seed;
+    let finished = simulation.play_back(replay, max_steps);
+
+    let model = &simulation.model;
+    if !finished {
+        log::warn!(
+            "Reached the step limit of {} before the replay ended",
+            max_steps
+        );
+    }
+    println!(
+        "Replay with seed {}: reached level {} with score {}, phase {:?}",
+        seed, model.level, model.score, model.phase
+    );
+
+    Ok(())
+}
+
+/// Validate the items, compile their and the enemies' scripts, and print the problems found.
+/// Returns `false` if there were any errors or warnings.
+pub fn check_items(config_path: &Path, assets_path: &Path) -> anyhow::Result<bool> {
+    let config = Config::load_headless(config_path)?;
+    let items = ItemAssets::load_headless(assets_path.join("items"))?;
+    let enemies = EnemyAssets::load_headless(assets_path.join("enemies"))?;
+    let report = crate::model::check_items(&config, &items, &enemies)?;
+    if report.is_empty() {
+        println!("All {} items are valid", items.assets.len());
+        return Ok(true);
+    }
+    print!("{}", report);
+    Ok(false)
+}
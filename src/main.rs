@@ -2,11 +2,13 @@ mod assets;
 mod config;
 mod controls;
 mod game;
+mod headless;
 mod main_menu;
 mod model;
 mod prelude;
 mod render;
 mod util;
+mod watcher;
 
 use geng::prelude::*;
 
@@ -14,6 +16,31 @@ use geng::prelude::*;
 struct Opts {
     #[clap(long)]
     config: Option<std::path::PathBuf>,
+    /// Seed for the random number generator, random if not specified.
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Play a game with random decisions without opening a window.
+    #[clap(long)]
+    headless: bool,
+    /// The maximum number of model updates in the headless mode.
+    #[clap(long, default_value_t = 1_000_000)]
+    max_steps: usize,
+    /// Save the recording of the run to the file when it ends.
+    /// Runs started with a retry are saved to numbered files next to it.
+    #[clap(long)]
+    record: Option<std::path::PathBuf>,
+    /// Play back the recorded run from the file.
+    #[clap(long)]
+    replay: Option<std::path::PathBuf>,
+    /// Validate the items, compile the item and enemy scripts, and exit with a non-zero code if there are any problems.
+    #[clap(long)]
+    check_items: bool,
+    /// Watch the item files and reload the changed items while playing.
+    #[clap(long)]
+    hot_reload: bool,
+    /// Place the items on the board manually at the start of every level.
+    #[clap(long)]
+    manual_placement: bool,
     #[clap(flatten)]
     geng: geng::CliArgs,
 }
@@ -24,6 +51,55 @@ fn main() {
 
     let opts: Opts = clap::Parser::parse();
 
+    if opts.check_items {
+        let config_path = opts
+            .config
+            .unwrap_or_else(|| run_dir().join("assets").join("config.ron"));
+        match headless::check_items(&config_path, &run_dir().join("assets")) {
+            Ok(true) => {}
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                log::error!("Item check failed: {:?}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let replay = match opts.replay.as_ref().map(model::Replay::load).transpose() {
+        Ok(replay) => replay,
+        Err(err) => {
+            log::error!("{:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    if opts.headless {
+        let result = match replay {
+            Some(replay) => headless::replay(replay, opts.max_steps),
+            None => {
+                let config_path = opts
+                    .config
+                    .unwrap_or_else(|| run_dir().join("assets").join("config.ron"));
+                headless::run(&config_path, opts.seed, opts.max_steps)
+            }
+        };
+        if let Err(err) = result {
+            log::error!("Headless run failed: {:?}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let run_options = game::RunOptions {
+        seed: opts.seed,
+        record: opts.record,
+        replay,
+        resume: None,
+        hot_reload: opts.hot_reload,
+        manual_placement: opts.manual_placement.then_some(true),
+    };
+
     let mut geng_options = geng::ContextOptions::default();
     geng_options.window.title = "Geng Game".to_string();
     geng_options.with_cli(&opts.geng);
@@ -56,7 +132,27 @@ fn main() {
             timer.tick().as_secs_f64()
         );
 
-        let state = main_menu::MainMenu::new(&geng, &Rc::new(assets), config, &Rc::new(items));
+        let enemies: assets::EnemyAssets =
+            geng::asset::Load::load(manager, &run_dir().join("assets").join("enemies"), &())
+                .await
+                .unwrap();
+
+        log::debug!(
+            "took {:.3}s to load enemy assets",
+            timer.tick().as_secs_f64()
+        );
+
+        model::validate_items(&config, &items).log();
+        model::validate_enemies(&enemies).log();
+
+        let state = main_menu::MainMenu::new(
+            &geng,
+            &Rc::new(assets),
+            config,
+            &Rc::new(items),
+            &Rc::new(enemies),
+            run_options,
+        );
         geng.run_state(state).await;
     });
 }
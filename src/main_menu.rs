@@ -1,10 +1,20 @@
-use crate::{controls::*, prelude::*};
+use crate::{
+    controls::*,
+    game::{load_saved_run, RunOptions},
+    prelude::*,
+};
 
 pub struct MainMenu {
     geng: Geng,
     assets: Rc<Assets>,
     config: Config,
     all_items: Rc<ItemAssets>,
+    all_enemies: Rc<EnemyAssets>,
+    options: RunOptions,
+    /// The unfinished run that can be continued.
+    saved_run: Option<SavedRun>,
+    /// Why the last run failed to start, e.g. the scripts did not compile.
+    error: Option<String>,
     camera: Camera2d,
     framebuffer_size: vec2<usize>,
 
@@ -13,6 +23,8 @@ pub struct MainMenu {
     touch_controller: TouchController,
 
     play_button: Aabb2<f32>,
+    continue_button: Aabb2<f32>,
+    placement_button: Aabb2<f32>,
     transition: Option<geng::state::Transition>,
 }
 
@@ -22,6 +34,8 @@ impl MainMenu {
         assets: &Rc<Assets>,
         config: Config,
         all_items: &Rc<ItemAssets>,
+        all_enemies: &Rc<EnemyAssets>,
+        options: RunOptions,
     ) -> Self {
         Self {
             geng: geng.clone(),
@@ -29,6 +43,10 @@ impl MainMenu {
             transition: None,
             config,
             all_items: all_items.clone(),
+            all_enemies: all_enemies.clone(),
+            options,
+            saved_run: load_saved_run(),
+            error: None,
             camera: Camera2d {
                 center: vec2::ZERO,
                 rotation: Angle::ZERO,
@@ -41,18 +59,29 @@ impl MainMenu {
             touch_controller: TouchController::new(),
 
             play_button: Aabb2::ZERO,
+            continue_button: Aabb2::ZERO,
+            placement_button: Aabb2::ZERO,
         }
     }
 
-    fn play(&mut self) {
-        self.transition = Some(geng::state::Transition::Push(Box::new(
-            crate::game::Game::new(
-                &self.geng,
-                &self.assets,
-                self.config.clone(),
-                &self.all_items,
-            ),
-        )));
+    fn play(&mut self, options: RunOptions) {
+        match crate::game::Game::new(
+            &self.geng,
+            &self.assets,
+            self.config.clone(),
+            &self.all_items,
+            &self.all_enemies,
+            options,
+        ) {
+            Ok(game) => {
+                self.error = None;
+                self.transition = Some(geng::state::Transition::Push(Box::new(game)));
+            }
+            Err(err) => {
+                log::error!("Failed to start the run: {:?}", err);
+                self.error = Some(format!("{:#}", err));
+            }
+        }
     }
 
     fn draw_at(
@@ -73,9 +102,24 @@ impl MainMenu {
         );
     }
 
+    fn manual_placement(&self) -> bool {
+        self.options
+            .manual_placement
+            .unwrap_or(self.config.manual_placement)
+    }
+
     fn handle_lmb(&mut self) {
         if self.play_button.contains(self.cursor_ui_pos) {
-            self.play();
+            self.play(self.options.clone());
+        } else if self.placement_button.contains(self.cursor_ui_pos) {
+            self.options.manual_placement = Some(!self.manual_placement());
+        } else if self.continue_button.contains(self.cursor_ui_pos) {
+            if let Some(saved) = self.saved_run.take() {
+                self.play(RunOptions {
+                    resume: Some(saved),
+                    ..self.options.clone()
+                });
+            }
         }
     }
 }
@@ -230,6 +274,76 @@ impl geng::State for MainMenu {
                 &self.camera,
                 framebuffer,
             );
+
+            // Continue
+            if self.saved_run.is_some() {
+                let pos = if portrait {
+                    vec2(0.0, -4.2)
+                } else {
+                    pos + vec2(0.0, -1.4)
+                };
+                self.continue_button = Aabb2::point(pos).extend_symmetric(vec2(1.5, 0.25));
+                let color = if self.continue_button.contains(self.cursor_ui_pos) {
+                    Color::try_from("#ffe7cd").unwrap()
+                } else {
+                    Color::try_from("#c9464b").unwrap()
+                };
+                self.geng.draw2d().draw2d(
+                    framebuffer,
+                    &self.camera,
+                    &draw2d::Text::unit(self.assets.font.clone(), "CONTINUE", color)
+                        .fit_into(self.continue_button),
+                );
+            } else {
+                self.continue_button = Aabb2::ZERO;
+            }
+
+            // Placement toggle
+            let pos = if portrait {
+                vec2(0.0, -4.7)
+            } else {
+                self.play_button.center() + vec2(0.0, -2.0)
+            };
+            self.placement_button = Aabb2::point(pos).extend_symmetric(vec2(1.5, 0.2));
+            let color = if self.placement_button.contains(self.cursor_ui_pos) {
+                Color::try_from("#ffe7cd").unwrap()
+            } else {
+                Color::try_from("#7a7a7a").unwrap()
+            };
+            let text = if self.manual_placement() {
+                "PLACEMENT: MANUAL"
+            } else {
+                "PLACEMENT: RANDOM"
+            };
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.camera,
+                &draw2d::Text::unit(self.assets.font.clone(), text, color)
+                    .fit_into(self.placement_button),
+            );
+        }
+
+        if let Some(error) = &self.error {
+            // The reason the run did not start
+            let pos = if portrait {
+                vec2(0.0, 4.6)
+            } else {
+                vec2(0.0, -3.6)
+            };
+            let max_lines = 4;
+            for (i, line) in error.lines().take(max_lines).enumerate() {
+                let pos = pos - vec2(0.0, i as f32 * 0.3);
+                self.geng.draw2d().draw2d(
+                    framebuffer,
+                    &self.camera,
+                    &draw2d::Text::unit(
+                        self.assets.font.clone(),
+                        line,
+                        Color::try_from("#c9464b").unwrap(),
+                    )
+                    .fit_into(Aabb2::point(pos).extend_symmetric(vec2(9.0, 0.25) / 2.0)),
+                );
+            }
         }
 
         {
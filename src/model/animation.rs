@@ -35,6 +35,8 @@ pub enum AnimationKind {
     },
     Dupe {
         kind: ItemKind,
+        /// The memory the copy starts with, if it is kept.
+        memory: Option<ItemMemory>,
     },
     Damage {
         from: vec2<Coord>,
@@ -0,0 +1,131 @@
+use super::*;
+
+/// A problem found in the items or references to them.
+#[derive(Debug, Clone)]
+pub struct ItemDiagnostic {
+    /// Name of the item (or the file) the problem is in.
+    pub source: Rc<str>,
+    pub severity: Severity,
+    /// Byte range in the script the diagnostic points at.
+    pub span: Option<std::ops::Range<usize>>,
+    /// Line and column (both starting at 1) of the start of the span.
+    pub location: Option<(usize, usize)>,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// All the problems found when loading, validating, or compiling the items.
+#[derive(Debug, Clone, Default)]
+pub struct ItemReport {
+    pub diagnostics: Vec<ItemDiagnostic>,
+}
+
+impl ItemDiagnostic {
+    /// A problem located in the script.
+    pub fn new(
+        source: Rc<str>,
+        severity: Severity,
+        script: &str,
+        span: Option<std::ops::Range<usize>>,
+        message: String,
+    ) -> Self {
+        let location = span.as_ref().map(|span| line_column(script, span.start));
+        Self {
+            source,
+            severity,
+            span,
+            location,
+            message,
+        }
+    }
+
+    pub fn error(source: impl Into<Rc<str>>, message: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            severity: Severity::Error,
+            span: None,
+            location: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn warning(source: impl Into<Rc<str>>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            ..Self::error(source, message)
+        }
+    }
+}
+
+impl ItemReport {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn extend(&mut self, other: ItemReport) {
+        self.diagnostics.extend(other.diagnostics);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+
+    /// Log every diagnostic with the matching level.
+    pub fn log(&self) {
+        for diagnostic in &self.diagnostics {
+            match diagnostic.severity {
+                Severity::Error => log::error!("{}", diagnostic),
+                Severity::Warning => log::warn!("{}", diagnostic),
+            }
+        }
+    }
+}
+
+impl std::error::Error for ItemReport {}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+impl fmt::Display for ItemDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)?;
+        if let Some((line, column)) = self.location {
+            write!(f, ":{}:{}", line, column)?;
+        }
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
+
+impl fmt::Display for ItemReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+/// Convert a byte offset into a line and a column, both starting at 1.
+fn line_column(script: &str, offset: usize) -> (usize, usize) {
+    let before = script.get(..offset).unwrap_or(script);
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |line| line.chars().count())
+        + 1;
+    (line, column)
+}
@@ -2,19 +2,52 @@ use super::*;
 
 #[derive(Debug)]
 pub struct QueuedEffect {
-    /// The trigger that proc'ed the effect.
-    pub trigger: Trigger,
-    /// The id of the item that proc'ed the effect.
-    pub proc_item: Id,
+    /// The trigger that proc'ed the effect, `None` for the effects of the enemies.
+    pub trigger: Option<Trigger>,
+    /// The id of the item that proc'ed the effect, `None` for the effects of the enemies.
+    pub proc_item: Option<Id>,
     pub effect: Effect,
 }
 
+/// An effect waiting for the items to react to it, before it is resolved.
+#[derive(Debug)]
+pub struct PendingEffect {
+    /// Taken out once all the items have reacted,
+    /// so a script holding on to the effect sees nothing after that.
+    pub effect: Option<QueuedEffect>,
+    pub cancelled: bool,
+}
+
+/// The name of the function in scripts reacting to the effects about to be resolved.
+pub const REACT_METHOD: &str = "react";
+
+/// An event item scripts can respond to.
+///
+/// Triggers for multiple items are queued and resolved one item at a time,
+/// ordered by the position of the item (left to right, top to bottom).
+/// `Destroyed` is resolved right away, before the item is removed.
 #[derive(Debug, Clone, Copy)]
 pub enum Trigger {
     Night,
     DayBonus,
     DayAction,
     Active,
+    /// The item has been put on the board.
+    Placed,
+    /// The item is about to be removed.
+    Destroyed,
+    /// The item has been moved by the night shift.
+    Shifted,
+    /// For all items, after the items of the new level have been placed.
+    LevelStart,
+    /// For all items, when the level is finished (won or lost).
+    LevelEnd,
+    /// For all items, after the enemy's own death handler.
+    EnemyKilled,
+    /// For all items on the board, when the player gets a new item.
+    ItemAcquired,
+    /// The tile of the item has become observed.
+    Observed,
 }
 
 #[derive(Debug)]
@@ -25,6 +58,8 @@ pub enum Effect {
     Damage {
         target: Id,
         damage: Rc<ScriptFunction>,
+        /// Added to the damage calculated by the function.
+        extra: Hp,
     },
     Bonus {
         from: vec2<Coord>,
@@ -44,6 +79,11 @@ pub enum Effect {
     Duplicate {
         item_id: Id,
     },
+    /// Keep the board item in place for the next shifts.
+    Anchor {
+        item: Id,
+        shifts: usize,
+    },
     /// Gain extra moves for this turn.
     GainMoves {
         moves: usize,
@@ -68,9 +108,47 @@ pub enum Effect {
     NewItem {
         kind: ItemKind,
     },
+    /// Move the entity to a free tile.
+    MoveEntity {
+        entity_id: Id,
+        target: vec2<Coord>,
+    },
+    /// Add a shield that absorbs the damage.
+    Shield {
+        entity_id: Id,
+        amount: Hp,
+    },
+    Heal {
+        entity_id: Id,
+        amount: Hp,
+    },
+    /// Spawn a new enemy on a free tile.
+    SpawnEnemy {
+        kind: EnemyKind,
+        position: vec2<Coord>,
+    },
+    /// Remove the item from the board and the inventory once its `Destroyed` effects are done.
+    RemoveItem {
+        item_id: Id,
+    },
 }
 
 impl Trigger {
+    pub const ALL: [Trigger; 12] = [
+        Trigger::Night,
+        Trigger::DayBonus,
+        Trigger::DayAction,
+        Trigger::Active,
+        Trigger::Placed,
+        Trigger::Destroyed,
+        Trigger::Shifted,
+        Trigger::LevelStart,
+        Trigger::LevelEnd,
+        Trigger::EnemyKilled,
+        Trigger::ItemAcquired,
+        Trigger::Observed,
+    ];
+
     /// The name of the method in scripts responsible for handling the trigger.
     pub fn method_name(&self) -> &'static str {
         match self {
@@ -78,6 +156,14 @@ impl Trigger {
             Self::DayBonus => "day_bonus",
             Self::DayAction => "day_action",
             Self::Active => "active",
+            Self::Placed => "on_placed",
+            Self::Destroyed => "on_destroyed",
+            Self::Shifted => "on_shifted",
+            Self::LevelStart => "on_level_start",
+            Self::LevelEnd => "on_level_end",
+            Self::EnemyKilled => "on_enemy_killed",
+            Self::ItemAcquired => "on_item_acquired",
+            Self::Observed => "on_observed",
         }
     }
 }
@@ -0,0 +1,74 @@
+use super::*;
+
+#[derive(Clone)]
+pub struct EnemyKind {
+    pub config: EnemyConfig,
+    pub script: Arc<Script>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnemyCategory {
+    /// Only appears when spawned by other enemies.
+    Minion,
+    Undead,
+    Beast,
+    Construct,
+}
+
+/// An event an enemy script can respond to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnemyTrigger {
+    Night,
+    Day,
+    Damaged,
+    Death,
+}
+
+/// A representation of the enemy used temporarily for scripts.
+pub struct ScriptEnemy<'a> {
+    pub model: Ref<'a, ModelState>,
+    pub effects: Rc<RefCell<Vec<Effect>>>,
+    pub entity_id: Id,
+    pub entity: &'a Entity,
+}
+
+impl EnemyKind {
+    /// The health of the enemy spawned on the level.
+    pub fn health(&self, level: usize) -> Hp {
+        let scaling = self.config.health_scaling.pow(level as u32 / 4);
+        self.config.health * scaling
+    }
+
+    /// Whether the enemy can appear on its own on the level.
+    pub fn spawns_on(&self, level: usize) -> bool {
+        !self.config.categories.contains(&EnemyCategory::Minion) && self.config.min_level <= level
+    }
+}
+
+impl EnemyTrigger {
+    pub const ALL: [EnemyTrigger; 4] = [
+        EnemyTrigger::Night,
+        EnemyTrigger::Day,
+        EnemyTrigger::Damaged,
+        EnemyTrigger::Death,
+    ];
+
+    /// The name of the function in scripts responsible for handling the trigger.
+    pub fn method_name(&self) -> &'static str {
+        match self {
+            Self::Night => "night",
+            Self::Day => "day",
+            Self::Damaged => "on_damaged",
+            Self::Death => "on_death",
+        }
+    }
+}
+
+impl std::fmt::Debug for EnemyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EnemyKind")
+            .field("config", &self.config)
+            .field("script", &"<hidden>")
+            .finish()
+    }
+}
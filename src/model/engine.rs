@@ -2,9 +2,10 @@ use super::*;
 
 use anyhow::Result;
 use rune::{
+    ast::Spanned,
+    diagnostics::{Diagnostic, FatalDiagnosticKind},
     runtime::RuntimeContext,
-    termcolor::{ColorChoice, StandardStream},
-    Context, ContextError, Diagnostics, Module, Source, Sources, Unit, Vm,
+    Context, ContextError, Diagnostics, Module, Source, SourceId, Sources, Unit, Vm,
 };
 
 pub struct Engine {
@@ -12,15 +13,17 @@ pub struct Engine {
     side_effects: Rc<RefCell<Vec<Effect>>>,
     context: Context,
     runtime: Arc<RuntimeContext>,
+    /// The maximum number of instructions executed by a single script call.
+    script_budget: usize,
 }
 
 impl Engine {
     pub fn new(
         model_state: Rc<RefCell<ModelState>>,
         side_effects: Rc<RefCell<Vec<Effect>>>,
+        script_budget: usize,
     ) -> Result<Self> {
-        let mut context = Context::with_default_modules()?;
-        context.install(item::module()?)?;
+        let context = script_context()?;
         let runtime = Arc::new(context.runtime()?);
 
         Ok(Self {
@@ -28,60 +31,111 @@ impl Engine {
             side_effects,
             context,
             runtime,
+            script_budget,
         })
     }
 
-    pub fn compile_items(&self, all_items: &ItemAssets) -> Result<Vec<ItemKind>> {
+    /// Run a script call with the instruction budget, so that an infinite loop
+    /// fails the call instead of freezing the game.
+    pub fn with_budget<T>(&self, call: impl FnOnce() -> T) -> T {
+        rune::runtime::budget::with(self.script_budget, call).call()
+    }
+
+    /// Compile the scripts of all items.
+    /// When `strict` is set, fails if any of the scripts does not compile,
+    /// otherwise the broken scripts are replaced with empty ones.
+    pub fn compile_items(&self, all_items: &ItemAssets, strict: bool) -> Result<Vec<ItemKind>> {
+        let mut report = ItemReport::default();
+        let library = LibrarySource::new(&all_items.library);
+        compile_library(&self.context, &library, &mut report)?;
         let mut items = Vec::with_capacity(all_items.assets.len());
         for item in all_items.assets.values() {
-            let mut diagnostics = Diagnostics::new();
-
-            let mut sources = Sources::new();
-            let script = item.script.as_deref().unwrap_or("");
-            sources.insert(Source::new(&item.config.name, script)?)?;
-
-            let unit = rune::prepare(&mut sources)
-                .with_context(&self.context)
-                .with_diagnostics(&mut diagnostics)
-                .build();
-
-            let script = if diagnostics.is_empty() {
-                unit.expect("Something failed but no diagnostics are available")
-            } else {
-                let mut writer = StandardStream::stdout(ColorChoice::Always);
-                writeln!(writer)?;
-                writeln!(
-                    writer,
-                    "Script compilation failed for item {}:",
-                    item.config.name
-                )?;
-                diagnostics.emit(&mut writer, &sources)?;
-                writeln!(writer)?;
-                Unit::default()
-            };
-
+            let script = compile_item(&self.context, &self.runtime, item, &library, &mut report)?;
             items.push(ItemKind {
                 config: item.config.clone(),
-                script: Arc::new(script),
+                script: script.unwrap_or_default(),
             });
         }
 
+        if strict && report.has_errors() {
+            return Err(anyhow::Error::new(report).context("item scripts failed to compile"));
+        }
+        report.log();
+
+        // Keep the order independent of the hashmap for the rng to be deterministic
+        items.sort_by(|a, b| a.config.name.cmp(&b.config.name));
+
         Ok(items)
     }
 
+    /// Compile a single item, failing with the report if its script
+    /// or the library has errors.
+    pub fn recompile_item(
+        &self,
+        item: &ItemAsset,
+        library: &ScriptLibrary,
+    ) -> Result<ItemKind, ItemReport> {
+        let mut report = ItemReport::default();
+        let library = LibrarySource::new(library);
+        let script = match compile_library(&self.context, &library, &mut report)
+            .and_then(|()| compile_item(&self.context, &self.runtime, item, &library, &mut report))
+        {
+            Ok(script) => script,
+            Err(err) => {
+                report.diagnostics.push(ItemDiagnostic::error(
+                    Rc::clone(&item.config.name),
+                    format!("{:?}", err),
+                ));
+                None
+            }
+        };
+        if report.has_errors() {
+            return Err(report);
+        }
+        report.log();
+
+        Ok(ItemKind {
+            config: item.config.clone(),
+            script: script.unwrap_or_default(),
+        })
+    }
+
+    /// Compile the scripts of all enemies.
+    /// Same as [Engine::compile_items], but enemies are allowed to not have a script.
+    pub fn compile_enemies(
+        &self,
+        all_enemies: &EnemyAssets,
+        strict: bool,
+    ) -> Result<Vec<EnemyKind>> {
+        let mut report = ItemReport::default();
+        let mut enemies = Vec::with_capacity(all_enemies.assets.len());
+        for enemy in all_enemies.assets.values() {
+            let script = compile_enemy(&self.context, &self.runtime, enemy, &mut report)?;
+            enemies.push(EnemyKind {
+                config: enemy.config.clone(),
+                script: script.unwrap_or_default(),
+            });
+        }
+
+        if strict && report.has_errors() {
+            return Err(anyhow::Error::new(report).context("enemy scripts failed to compile"));
+        }
+        report.log();
+
+        // Keep the order independent of the hashmap for the rng to be deterministic
+        enemies.sort_by(|a, b| a.config.name.cmp(&b.config.name));
+
+        Ok(enemies)
+    }
+
+    /// Create a new item and call its `init` handler (if it is defined) with the item's memory.
     pub fn init_item(&self, kind: ItemKind) -> Result<InventoryItem> {
-        // TODO: check if state works
+        let memory = item::Memory::new(ItemMemory::default());
         let vm = Vm::new(Arc::clone(&self.runtime), Arc::clone(&kind.script));
         if let Ok(init) = vm.lookup_function(["init"]) {
-            init.call(()).into_result()?;
+            self.with_budget(|| init.call((memory.clone(),)))
+                .into_result()?;
         }
-        let state = ScriptState {
-            stack: rune::alloc::prelude::TryClone::try_clone(vm.stack())
-                .expect("failed to clone script stack"),
-        };
-
-        // let options = CallFnOptions::new().eval_ast(false).rewind_scope(false); // Retain variables
-        // call!(&self.inner, options, &mut state, &kind.script, "init", ());
 
         let base_stats = kind.config.base_stats.clone();
 
@@ -90,7 +144,8 @@ impl Engine {
             side_effects: Rc::clone(&self.side_effects),
             on_board: None,
             kind,
-            state,
+            tier: 1,
+            memory: memory.into_inner(),
             turns_on_board: 0,
             base_stats,
             perm_stats: ItemStats::default(),
@@ -98,7 +153,7 @@ impl Engine {
         })
     }
 
-    /// Call the item's trigger handler (if it is defined) and return the new item state.
+    /// Call the item's trigger handler (if it is defined) and return the new memory of the item.
     /// Side effects produced by the script are put into [ModelState].
     ///
     /// *NOTE*: it borrows [ModelState] and mutates `side_effects`.
@@ -107,7 +162,7 @@ impl Engine {
         item: &InventoryItem,
         board_item: &BoardItem,
         method: &str,
-    ) -> Result<ScriptState> {
+    ) -> Result<ItemMemory> {
         log::debug!(
             "Item trigger {:?} for {:?}, item: {:?}",
             method,
@@ -116,21 +171,399 @@ impl Engine {
         );
 
         let script_item = item::Item::from_real(item, board_item);
+        let memory = script_item.memory();
 
-        let vm = Vm::with_stack(
-            Arc::clone(&self.runtime),
-            Arc::clone(&item.kind.script),
-            item.state.clone().stack,
-        );
+        let vm = Vm::new(Arc::clone(&self.runtime), Arc::clone(&item.kind.script));
         if let Ok(fun) = vm.lookup_function([method]) {
-            fun.call((script_item,)).into_result()?;
+            self.with_budget(|| fun.call((script_item,)))
+                .into_result()?;
         }
 
-        Ok(ScriptState {
-            stack: rune::alloc::prelude::TryClone::try_clone(vm.stack())
-                .expect("failed to clone script stack"),
+        Ok(memory.into_inner())
+    }
+
+    /// Call the item's `react` handler (if it is defined) with the effect about to be resolved,
+    /// and return the new memory of the item.
+    /// Side effects produced by the script are put into [ModelState].
+    ///
+    /// *NOTE*: it borrows [ModelState] and mutates `side_effects`.
+    pub fn item_react(
+        &self,
+        item: &InventoryItem,
+        board_item: &BoardItem,
+        effect: &Rc<RefCell<PendingEffect>>,
+    ) -> Result<ItemMemory> {
+        let script_item = item::Item::from_real(item, board_item);
+        let memory = script_item.memory();
+        let reaction = item::Reaction::new(Rc::clone(&self.model_state), Rc::clone(effect));
+
+        let vm = Vm::new(Arc::clone(&self.runtime), Arc::clone(&item.kind.script));
+        if let Ok(fun) = vm.lookup_function([REACT_METHOD]) {
+            self.with_budget(|| fun.call((script_item, reaction)))
+                .into_result()?;
+        }
+
+        Ok(memory.into_inner())
+    }
+
+    /// Whether the item's script defines the function.
+    pub fn handles(&self, kind: &ItemKind, method: &str) -> bool {
+        let vm = Vm::new(Arc::clone(&self.runtime), Arc::clone(&kind.script));
+        vm.lookup_function([method]).is_ok()
+    }
+
+    /// Call the enemy's trigger handler (if it is defined).
+    /// Side effects produced by the script are put into [ModelState].
+    ///
+    /// *NOTE*: it borrows [ModelState] and mutates `side_effects`.
+    pub fn enemy_trigger(
+        &self,
+        kind: &EnemyKind,
+        entity_id: Id,
+        entity: &Entity,
+        trigger: EnemyTrigger,
+    ) -> Result<()> {
+        log::debug!(
+            "Enemy trigger {:?} for {:?}, entity: {:?}",
+            trigger,
+            kind.config.name,
+            entity_id,
+        );
+
+        let script_enemy = enemy::Enemy::from_real(
+            Rc::clone(&self.model_state),
+            Rc::clone(&self.side_effects),
+            entity_id,
+            entity,
+        );
+
+        let vm = Vm::new(Arc::clone(&self.runtime), Arc::clone(&kind.script));
+        if let Ok(fun) = vm.lookup_function([trigger.method_name()]) {
+            self.with_budget(|| fun.call((script_enemy,)))
+                .into_result()?;
+        }
+        Ok(())
+    }
+}
+
+fn script_context() -> Result<Context> {
+    let mut context = Context::with_default_modules()?;
+    context.install(item::module()?)?;
+    context.install(enemy::module()?)?;
+    Ok(context)
+}
+
+/// The library modules put into a single source:
+/// `pub mod lib { pub mod <name> { <module> } ... }`.
+struct LibrarySource<'a> {
+    library: &'a ScriptLibrary,
+    source: String,
+    /// Byte offset of every module's code in the source.
+    offsets: Vec<usize>,
+}
+
+impl<'a> LibrarySource<'a> {
+    /// The name of the source in diagnostics.
+    const NAME: &'static str = "_lib";
+
+    fn new(library: &'a ScriptLibrary) -> Self {
+        let mut source = String::from("pub mod lib {\n");
+        let mut offsets = Vec::with_capacity(library.modules.len());
+        for (name, module) in &library.modules {
+            source += &format!("pub mod {} {{\n", name);
+            offsets.push(source.len());
+            source += module;
+            source += "\n}\n";
+        }
+        source += "}\n";
+        Self {
+            library,
+            source,
+            offsets,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.library.modules.is_empty()
+    }
+
+    /// Point the diagnostic at the module file instead of the combined source.
+    fn diagnostic(
+        &self,
+        severity: Severity,
+        span: Option<std::ops::Range<usize>>,
+        message: String,
+    ) -> ItemDiagnostic {
+        let module = span.as_ref().and_then(|span| {
+            self.offsets
+                .iter()
+                .rposition(|&offset| offset <= span.start)
+        });
+        let Some(index) = module else {
+            return ItemDiagnostic {
+                severity,
+                ..ItemDiagnostic::error(Self::NAME, message)
+            };
+        };
+
+        let (name, module) = &self.library.modules[index];
+        let offset = self.offsets[index];
+        let span = span.map(|span| {
+            let start = (span.start - offset).min(module.len());
+            let end = span.end.saturating_sub(offset).clamp(start, module.len());
+            start..end
+        });
+        ItemDiagnostic::new(
+            format!("{}/{}.rn", Self::NAME, name).into(),
+            severity,
+            module,
+            span,
+            message,
+        )
+    }
+}
+
+/// Compile the library on its own, putting its problems into the report.
+/// Item scripts only report their own problems, so the library ones are not repeated for every item.
+fn compile_library(
+    context: &Context,
+    library: &LibrarySource<'_>,
+    report: &mut ItemReport,
+) -> Result<()> {
+    if library.is_empty() {
+        return Ok(());
+    }
+
+    let mut diagnostics = Diagnostics::new();
+    let mut sources = Sources::new();
+    sources.insert(Source::new(LibrarySource::NAME, &library.source)?)?;
+    let _ = rune::prepare(&mut sources)
+        .with_context(context)
+        .with_diagnostics(&mut diagnostics)
+        .build();
+
+    for diagnostic in diagnostics.diagnostics() {
+        let Some((severity, span, message)) = describe_diagnostic(diagnostic) else {
+            continue;
+        };
+        report
+            .diagnostics
+            .push(library.diagnostic(severity, span, message));
+    }
+    Ok(())
+}
+
+/// Compile the item script, putting all the problems into the report.
+/// Returns `None` if the compilation failed, or the script cannot be used.
+fn compile_item(
+    context: &Context,
+    runtime: &Arc<RuntimeContext>,
+    item: &ItemAsset,
+    library: &LibrarySource<'_>,
+    report: &mut ItemReport,
+) -> Result<Option<Arc<Unit>>> {
+    let handlers: Vec<&str> = Trigger::ALL
+        .iter()
+        .map(|trigger| trigger.method_name())
+        .chain([REACT_METHOD])
+        .collect();
+    let Some(unit) = compile_script(
+        context,
+        runtime,
+        &item.config.name,
+        item.script.as_deref(),
+        Some(library),
+        &handlers,
+        report,
+    )?
+    else {
+        return Ok(None);
+    };
+
+    // Such scripts compile, but fail every time the item is created
+    let script = item.script.as_deref().unwrap_or("");
+    if let Some(span) = no_argument_init(script) {
+        report.diagnostics.push(ItemDiagnostic::new(
+            Rc::clone(&item.config.name),
+            Severity::Error,
+            script,
+            Some(span),
+            "`init` takes the memory of the item as the argument: `pub fn init(memory)`".to_owned(),
+        ));
+        return Ok(None);
+    }
+
+    Ok(Some(unit))
+}
+
+/// The span of the `init` handler declared without arguments, if there is one.
+fn no_argument_init(script: &str) -> Option<std::ops::Range<usize>> {
+    script
+        .match_indices("fn init")
+        .find_map(|(start, function)| {
+            let args = script[start + function.len()..]
+                .trim_start()
+                .strip_prefix('(')?;
+            args.trim_start()
+                .starts_with(')')
+                .then(|| start..start + function.len())
         })
+}
+
+/// Compile the enemy script, if it has one.
+fn compile_enemy(
+    context: &Context,
+    runtime: &Arc<RuntimeContext>,
+    enemy: &EnemyAsset,
+    report: &mut ItemReport,
+) -> Result<Option<Arc<Unit>>> {
+    let Some(script) = enemy.script.as_deref() else {
+        // Enemies without a script just stand there
+        return Ok(None);
+    };
+    let handlers = EnemyTrigger::ALL.map(|trigger| trigger.method_name());
+    compile_script(
+        context,
+        runtime,
+        &enemy.config.name,
+        Some(script),
+        None,
+        &handlers,
+        report,
+    )
+}
+
+/// The severity, the span and the message of a compilation diagnostic.
+fn describe_diagnostic(
+    diagnostic: &Diagnostic,
+) -> Option<(Severity, Option<std::ops::Range<usize>>, String)> {
+    match diagnostic {
+        Diagnostic::Fatal(fatal) => {
+            let span = match fatal.kind() {
+                FatalDiagnosticKind::CompileError(error) => Some(error.span().range()),
+                _ => None,
+            };
+            Some((Severity::Error, span, fatal.to_string()))
+        }
+        Diagnostic::Warning(warning) => Some((
+            Severity::Warning,
+            Some(warning.span().range()),
+            warning.to_string(),
+        )),
+        _ => None,
+    }
+}
+
+/// The source the diagnostic comes from.
+fn diagnostic_source(diagnostic: &Diagnostic) -> Option<SourceId> {
+    match diagnostic {
+        Diagnostic::Fatal(fatal) => Some(fatal.source_id()),
+        Diagnostic::Warning(warning) => Some(warning.source_id()),
+        _ => None,
+    }
+}
+
+/// Compile the script and check that it defines at least one of the `handlers`.
+/// The `library` (if given) is compiled alongside, but its problems are not reported.
+fn compile_script(
+    context: &Context,
+    runtime: &Arc<RuntimeContext>,
+    name: &Rc<str>,
+    script: Option<&str>,
+    library: Option<&LibrarySource<'_>>,
+    handlers: &[&str],
+    report: &mut ItemReport,
+) -> Result<Option<Arc<Unit>>> {
+    let mut diagnostics = Diagnostics::new();
+
+    let mut sources = Sources::new();
+    let script = script.unwrap_or("");
+    let script_id = sources.insert(Source::new(name, script)?)?;
+    if let Some(library) = library.filter(|library| !library.is_empty()) {
+        sources.insert(Source::new(LibrarySource::NAME, &library.source)?)?;
+    }
+
+    let unit = rune::prepare(&mut sources)
+        .with_context(context)
+        .with_diagnostics(&mut diagnostics)
+        .build();
+
+    let mut errors = 0;
+    for diagnostic in diagnostics.diagnostics() {
+        let Some((severity, span, message)) = describe_diagnostic(diagnostic) else {
+            continue;
+        };
+        if diagnostic_source(diagnostic) != Some(script_id) {
+            // Reported by `compile_library`
+            continue;
+        }
+        if severity == Severity::Error {
+            errors += 1;
+        }
+        report.diagnostics.push(ItemDiagnostic::new(
+            Rc::clone(name),
+            severity,
+            script,
+            span,
+            message,
+        ));
     }
+
+    let Ok(unit) = unit else {
+        if errors == 0 {
+            // Something failed but no diagnostics are available (or they are in the library)
+            report.diagnostics.push(ItemDiagnostic::error(
+                Rc::clone(name),
+                "failed to build the script",
+            ));
+        }
+        return Ok(None);
+    };
+    let unit = Arc::new(unit);
+
+    let vm = Vm::new(Arc::clone(runtime), Arc::clone(&unit));
+    if !handlers
+        .iter()
+        .any(|&handler| vm.lookup_function([handler]).is_ok())
+    {
+        report.diagnostics.push(ItemDiagnostic::warning(
+            Rc::clone(name),
+            format!(
+                "the script does not handle any of the triggers: {}",
+                handlers.join(", ")
+            ),
+        ));
+    }
+
+    Ok(Some(unit))
+}
+
+/// Validate the items and the references to them, and compile all scripts without running anything.
+pub fn check_items(
+    config: &Config,
+    all_items: &ItemAssets,
+    all_enemies: &EnemyAssets,
+) -> Result<ItemReport> {
+    let mut report = validate_items(config, all_items);
+    report
+        .diagnostics
+        .extend(validate_enemies(all_enemies).diagnostics);
+
+    let context = script_context()?;
+    let runtime = Arc::new(context.runtime()?);
+    let library = LibrarySource::new(&all_items.library);
+    compile_library(&context, &library, &mut report)?;
+    for (_, item) in all_items.assets.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+        compile_item(&context, &runtime, item, &library, &mut report)?;
+    }
+    for (_, enemy) in all_enemies
+        .assets
+        .iter()
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+    {
+        compile_enemy(&context, &runtime, enemy, &mut report)?;
+    }
+    Ok(report)
 }
 
 pub mod item {
@@ -154,9 +587,11 @@ pub mod item {
         module.function_meta(Item::find_multiple)?;
         module.function_meta(Item::find_nearby)?;
         module.function_meta(Item::find_random)?;
+        module.function_meta(Item::count_items)?;
         module.function_meta(Item::duplicate)?;
         module.function_meta(Item::rng_float)?;
         module.function_meta(Item::gain_moves)?;
+        module.function_meta(Item::anchor)?;
         module.function_meta(Item::portal)?;
         module.function_meta(Item::swap_with)?;
         module.function_meta(Item::grid_bounds)?;
@@ -165,11 +600,48 @@ pub mod item {
         module.function_meta(Item::is_observed)?;
         module.function_meta(Item::random_kind)?;
         module.function_meta(Item::use_item)?;
+        module.function_meta(Item::enemies)?;
+        module.function_meta(Item::nearest_enemy)?;
+        module.function_meta(Item::player)?;
+        module.function_meta(Item::player_position)?;
+        module.function_meta(Item::distance_to)?;
+
+        module.ty::<Memory>()?;
+        module.function_meta(Memory::get)?;
+        module.function_meta(Memory::get_or)?;
+        module.function_meta(Memory::set)?;
+        module.function_meta(Memory::add)?;
+        module.function_meta(Memory::remove)?;
+
+        module.ty::<EntityInfo>()?;
+        module.ty::<Fraction>()?;
+
+        module.ty::<Reaction>()?;
+        module.function_meta(Reaction::kind)?;
+        module.function_meta(Reaction::source)?;
+        module.function_meta(Reaction::target_item)?;
+        module.function_meta(Reaction::target_entity)?;
+        module.function_meta(Reaction::cancel)?;
+        module.function_meta(Reaction::is_cancelled)?;
+        module.function_meta(Reaction::add_damage)?;
+        module.function_meta(Reaction::bonus)?;
+        module.function_meta(Reaction::set_bonus)?;
+        module.function_meta(Reaction::redirect)?;
 
         module.ty::<Position>()?;
         module.ty::<Bounds>()?;
         module.ty::<Stats>()?;
+        module.function_meta(Stats::new)?;
+        module.function_meta(Stats::damage)?;
+        module.function_meta(Stats::with_damage)?;
+        module.function_meta(Stats::with_range)?;
+        module.function_meta(Stats::with_charges)?;
+        module.function_meta(Stats::with_heal)?;
+        module.function_meta(Stats::with_light)?;
+        module.function_meta(Stats::with_moves)?;
         module.ty::<Filter>()?;
+        module.function_meta(Filter::not)?;
+        module.ty::<Stat>()?;
         module.ty::<Target>()?;
         module.ty::<Category>()?;
 
@@ -185,15 +657,235 @@ pub mod item {
         position: Position,
         #[rune(get)]
         turns_on_board: usize,
+        /// Starts at 1, goes up when the copies are merged.
+        #[rune(get)]
+        tier: usize,
         #[rune(get)]
         stats: Stats,
+        #[rune(get)]
+        memory: Memory,
     }
 
+    /// Values the item remembers between script calls: `item.memory.get("charges")`.
+    /// Only the memory of the item whose script is called is kept after the call.
     #[derive(Debug, Clone, rune::Any)]
-    #[rune(constructor)]
+    pub struct Memory {
+        inner: Rc<RefCell<ItemMemory>>,
+    }
+
+    impl Memory {
+        pub fn new(memory: ItemMemory) -> Self {
+            Self {
+                inner: Rc::new(RefCell::new(memory)),
+            }
+        }
+
+        pub fn into_inner(self) -> ItemMemory {
+            self.inner.borrow().clone()
+        }
+
+        #[rune::function]
+        fn get(&self, key: &str) -> Option<i64> {
+            self.inner.borrow().get(key)
+        }
+
+        #[rune::function]
+        fn get_or(&self, key: &str, default: i64) -> i64 {
+            self.inner.borrow().get(key).unwrap_or(default)
+        }
+
+        #[rune::function]
+        fn set(&self, key: &str, value: i64) {
+            self.inner.borrow_mut().set(key, value)
+        }
+
+        /// Add to the value (missing is zero) and return the result.
+        #[rune::function]
+        fn add(&self, key: &str, amount: i64) -> i64 {
+            let mut memory = self.inner.borrow_mut();
+            let value = memory.get(key).unwrap_or(0) + amount;
+            memory.set(key, value);
+            value
+        }
+
+        #[rune::function]
+        fn remove(&self, key: &str) -> Option<i64> {
+            self.inner.borrow_mut().remove(key)
+        }
+    }
+
+    /// The effect about to be resolved, passed to the `react` handler as `Effect`.
+    /// Changes made to it apply to the effect itself.
+    #[derive(Clone, rune::Any)]
+    #[rune(name = Effect)]
+    pub struct Reaction {
+        model_state: Rc<RefCell<ModelState>>,
+        inner: Rc<RefCell<PendingEffect>>,
+    }
+
+    impl Reaction {
+        pub fn new(
+            model_state: Rc<RefCell<ModelState>>,
+            inner: Rc<RefCell<PendingEffect>>,
+        ) -> Self {
+            Self { model_state, inner }
+        }
+
+        /// One of `"Damage"`, `"Bonus"`, `"Destroy"`, `"Duplicate"`, `"Transform"`, `"Use"`.
+        #[rune::function]
+        fn kind(&self) -> String {
+            self.inner.borrow().kind_name().to_owned()
+        }
+
+        /// The item that produced the effect.
+        #[rune::function]
+        fn source(&self) -> Option<Item> {
+            let id = self.inner.borrow().effect.as_ref()?.proc_item?;
+            item_on_board(&self.model_state.borrow(), id)
+        }
+
+        #[rune::function]
+        fn target_item(&self) -> Option<Item> {
+            let model = self.model_state.borrow();
+            let id = self.inner.borrow().target_item(&model)?;
+            item_on_board(&model, id)
+        }
+
+        #[rune::function]
+        fn target_entity(&self) -> Option<EntityInfo> {
+            let id = self.inner.borrow().target_entity()?;
+            let model = self.model_state.borrow();
+            model.entities.get(id).map(EntityInfo::from)
+        }
+
+        /// Prevent the effect from happening, the rest of the items do not react to it.
+        #[rune::function]
+        fn cancel(&self) {
+            self.inner.borrow_mut().cancelled = true;
+        }
+
+        #[rune::function]
+        fn is_cancelled(&self) -> bool {
+            self.inner.borrow().cancelled
+        }
+
+        /// Returns `false` if the effect is not damage.
+        #[rune::function]
+        fn add_damage(&self, amount: Hp) -> bool {
+            self.inner.borrow_mut().add_damage(amount)
+        }
+
+        #[rune::function]
+        fn bonus(&self) -> Option<Stats> {
+            self.inner.borrow().bonus().cloned().map(Stats::from)
+        }
+
+        /// Returns `false` if the effect is not a bonus.
+        #[rune::function]
+        fn set_bonus(&self, stats: Stats) -> bool {
+            self.inner.borrow_mut().set_bonus(stats.into())
+        }
+
+        /// Apply the effect to another item on the board.
+        /// Returns `false` if the effect does not target items.
+        #[rune::function]
+        fn redirect(&self, target: &Item) -> bool {
+            let Some(id) = target.inventory.on_board else {
+                return false;
+            };
+            let model = self.model_state.borrow();
+            self.inner.borrow_mut().redirect(&model, id)
+        }
+    }
+
+    fn item_on_board(model: &ModelState, id: Id) -> Option<Item> {
+        let board = model.items.get(id)?;
+        let item = model.player.items.get(board.item_id)?;
+        Some(Item::from_real(item, board))
+    }
+
+    /// A snapshot of an entity on the board, accessible as `Entity` in scripts.
+    #[derive(Debug, Clone, rune::Any)]
+    #[rune(name = Entity)]
+    pub struct EntityInfo {
+        #[rune(get)]
+        position: Position,
+        #[rune(get)]
+        health: Hp,
+        #[rune(get)]
+        max_health: Hp,
+        #[rune(get)]
+        shield: Hp,
+        /// Name of the enemy kind, or `"Player"`.
+        #[rune(get)]
+        kind: String,
+        #[rune(get)]
+        fraction: Fraction,
+    }
+
+    /// Stats of the item, missing ones are zero.
+    /// Constructed with `Stats::new().with_damage(2)` to be used as a bonus,
+    /// or with `Stats::damage(2)` when only the damage is needed.
+    #[derive(Debug, Clone, Default, rune::Any)]
     pub struct Stats {
         #[rune(get)]
         damage: Hp,
+        #[rune(get)]
+        range: Hp,
+        #[rune(get)]
+        charges: Hp,
+        #[rune(get)]
+        heal: Hp,
+        #[rune(get)]
+        light: Hp,
+        #[rune(get)]
+        moves: Hp,
+    }
+
+    impl Stats {
+        #[rune::function(path = Self::new)]
+        fn new() -> Self {
+            Self::default()
+        }
+
+        /// Stats with only the damage.
+        #[rune::function(path = Self::damage)]
+        fn damage(damage: Hp) -> Self {
+            Self {
+                damage,
+                ..Self::default()
+            }
+        }
+
+        #[rune::function]
+        fn with_damage(self, damage: Hp) -> Self {
+            Self { damage, ..self }
+        }
+
+        #[rune::function]
+        fn with_range(self, range: Hp) -> Self {
+            Self { range, ..self }
+        }
+
+        #[rune::function]
+        fn with_charges(self, charges: Hp) -> Self {
+            Self { charges, ..self }
+        }
+
+        #[rune::function]
+        fn with_heal(self, heal: Hp) -> Self {
+            Self { heal, ..self }
+        }
+
+        #[rune::function]
+        fn with_light(self, light: Hp) -> Self {
+            Self { light, ..self }
+        }
+
+        #[rune::function]
+        fn with_moves(self, moves: Hp) -> Self {
+            Self { moves, ..self }
+        }
     }
 
     #[derive(Debug, Clone, rune::Any)]
@@ -204,6 +896,24 @@ pub mod item {
         Category(#[rune(get)] Category),
         #[rune(constructor)]
         Named(#[rune(get)] String),
+        #[rune(constructor)]
+        Tag(#[rune(get)] String),
+        /// Every one of the filters matches: `Filter::All([Filter::Tag("fire"), Filter::OnBoard])`.
+        #[rune(constructor)]
+        All(Vec<Filter>),
+        /// At least one of the filters matches.
+        #[rune(constructor)]
+        Any(Vec<Filter>),
+        /// Constructed with `Filter::not(filter)`.
+        Not(Box<Filter>),
+        /// The item has the stat, even if it is zero.
+        #[rune(constructor)]
+        HasStat(#[rune(get)] Stat),
+        #[rune(constructor)]
+        OnBoard,
+        /// In the inventory, but not on the board.
+        #[rune(constructor)]
+        InInventory,
     }
 
     #[derive(Debug, Clone, Copy, rune::Any)]
@@ -253,10 +963,17 @@ pub mod item {
 
                 position: board_item.position.into(),
                 turns_on_board: item.turns_on_board,
+                tier: item.tier,
                 stats: item.current_stats().into(),
+                memory: Memory::new(item.memory.clone()),
             }
         }
 
+        /// The handle to the memory, that stays valid after the script call.
+        pub fn memory(&self) -> Memory {
+            self.memory.clone()
+        }
+
         pub fn as_script(&self) -> ScriptItem<'_> {
             ScriptItem {
                 model: self.inventory.model_state.borrow(),
@@ -272,8 +989,13 @@ pub mod item {
         }
 
         #[rune::function]
-        fn damage_all_nearby(&self, range: Option<Coord>, damage: ScriptFunction) {
-            self.as_script().damage_all_nearby(range, damage)
+        fn damage_all_nearby(
+            &self,
+            range: Option<Coord>,
+            target: Option<Target>,
+            damage: ScriptFunction,
+        ) {
+            self.as_script().damage_all_nearby(range, target, damage)
         }
 
         #[rune::function]
@@ -359,6 +1081,13 @@ pub mod item {
             self.get_item_board(id)
         }
 
+        /// The number of items the player has (on the board or not) that match the filter.
+        #[rune::function]
+        fn count_items(&self, filter: Filter) -> usize {
+            self.as_script()
+                .count_items(filter.into_filter(&self.inventory.kind.config.name))
+        }
+
         #[rune::function]
         fn find_random(&self, filter: Filter) -> Option<Item> {
             let id = self
@@ -386,7 +1115,7 @@ pub mod item {
 
         #[rune::function]
         fn rng_float(&self) -> f32 {
-            thread_rng().gen()
+            self.as_script().rng_float()
         }
 
         #[rune::function]
@@ -394,6 +1123,12 @@ pub mod item {
             self.as_script().gain_moves(moves)
         }
 
+        /// Keep the item in place for the next `shifts` shifts.
+        #[rune::function]
+        fn anchor(&self, shifts: usize) {
+            self.as_script().anchor(shifts)
+        }
+
         #[rune::function]
         fn portal(&self) {
             self.as_script().portal()
@@ -401,8 +1136,11 @@ pub mod item {
 
         #[rune::function]
         fn swap_with(&self, target: &Item) {
-            self.as_script()
-                .swap_with(target.inventory.on_board.unwrap());
+            let Some(id) = target.inventory.on_board else {
+                log::error!("Cannot swap with an item that is not on the board");
+                return;
+            };
+            self.as_script().swap_with(id);
         }
 
         #[rune::function]
@@ -429,8 +1167,9 @@ pub mod item {
         /// Excluding kind of the item.
         #[rune::function]
         fn random_kind(&self, category: Option<Category>) -> Option<String> {
-            let mut rng = thread_rng();
-            self.as_script()
+            let script = self.as_script();
+            let mut rng = script.model.rng.borrow_mut();
+            script
                 .model
                 .all_items
                 .iter()
@@ -439,15 +1178,73 @@ pub mod item {
                         && category
                             .map_or(true, |category| item.config.categories.contains(&category))
                 })
-                .choose(&mut rng)
+                .choose(&mut *rng)
                 .map(|kind| kind.config.name.to_string())
         }
 
-        /// Excluding kind of the item.
         #[rune::function]
         fn use_item(&self, target: Item) {
+            let Some(id) = target.inventory.on_board else {
+                log::error!("Cannot use an item that is not on the board");
+                return;
+            };
+            self.as_script().use_item(id)
+        }
+
+        #[rune::function]
+        fn enemies(&self) -> Vec<EntityInfo> {
             self.as_script()
-                .use_item(target.inventory.on_board.unwrap())
+                .enemies()
+                .into_iter()
+                .flat_map(|id| self.get_entity(id))
+                .collect()
+        }
+
+        #[rune::function]
+        fn nearest_enemy(&self) -> Option<EntityInfo> {
+            let id = self.as_script().nearest_enemy()?;
+            self.get_entity(id)
+        }
+
+        #[rune::function]
+        fn player(&self) -> Option<EntityInfo> {
+            let id = self.as_script().player()?;
+            self.get_entity(id)
+        }
+
+        #[rune::function]
+        fn player_position(&self) -> Option<Position> {
+            let id = self.as_script().player()?;
+            self.get_entity(id).map(|player| player.position)
+        }
+
+        /// Number of steps (diagonal included) from the item to the position.
+        #[rune::function]
+        fn distance_to(&self, position: Position) -> Coord {
+            self.as_script().distance_to(position.into())
+        }
+
+        fn get_entity(&self, id: Id) -> Option<EntityInfo> {
+            let script = self.as_script();
+            let entity = script.model.entities.get(id)?;
+            Some(EntityInfo::from(entity))
+        }
+    }
+
+    impl From<&Entity> for EntityInfo {
+        fn from(entity: &Entity) -> Self {
+            let kind = match &entity.kind {
+                EntityKind::Enemy(name) => name.to_string(),
+                EntityKind::Player => "Player".to_string(),
+            };
+            Self {
+                position: entity.position.into(),
+                health: entity.health.value(),
+                max_health: entity.health.max(),
+                shield: entity.shield,
+                kind,
+                fraction: entity.fraction,
+            }
         }
     }
 
@@ -455,25 +1252,183 @@ pub mod item {
         fn from(value: ItemStats) -> Self {
             Self {
                 damage: value.damage.unwrap_or_default(),
+                range: value.range.unwrap_or_default(),
+                charges: value.charges.unwrap_or_default(),
+                heal: value.heal.unwrap_or_default(),
+                light: value.light.unwrap_or_default(),
+                moves: value.moves.unwrap_or_default(),
             }
         }
     }
 
     impl From<Stats> for ItemStats {
+        /// Zero stats are left out, so that a bonus does not add the stats the item did not have.
         fn from(value: Stats) -> Self {
+            let stat = |value: Hp| (value != 0).then_some(value);
             Self {
-                damage: Some(value.damage),
+                damage: stat(value.damage),
+                range: stat(value.range),
+                charges: stat(value.charges),
+                heal: stat(value.heal),
+                light: stat(value.light),
+                moves: stat(value.moves),
             }
         }
     }
 
     impl Filter {
+        #[rune::function(path = Self::not)]
+        fn not(filter: Filter) -> Self {
+            Self::Not(Box::new(filter))
+        }
+
         fn into_filter(self, this: &Rc<str>) -> ItemFilter {
+            let all = |filters: Vec<Filter>| {
+                filters
+                    .into_iter()
+                    .map(|filter| filter.into_filter(this))
+                    .collect()
+            };
             match self {
                 Filter::This => ItemFilter::Named(Rc::clone(this)),
                 Filter::Category(cat) => ItemFilter::Category(cat),
                 Filter::Named(name) => ItemFilter::Named(name.into()),
+                Filter::Tag(tag) => ItemFilter::Tag(tag.into()),
+                Filter::All(filters) => ItemFilter::All(all(filters)),
+                Filter::Any(filters) => ItemFilter::Any(all(filters)),
+                Filter::Not(filter) => ItemFilter::Not(Box::new(filter.into_filter(this))),
+                Filter::HasStat(stat) => ItemFilter::HasStat(stat),
+                Filter::OnBoard => ItemFilter::OnBoard,
+                Filter::InInventory => ItemFilter::InInventory,
             }
         }
     }
 }
+
+pub mod enemy {
+    use super::*;
+
+    use super::item::Position;
+
+    pub fn module() -> Result<Module, ContextError> {
+        let mut module = Module::new();
+
+        module.ty::<Enemy>()?;
+        module.function_meta(Enemy::player_position)?;
+        module.function_meta(Enemy::step_towards_player)?;
+        module.function_meta(Enemy::move_to)?;
+        module.function_meta(Enemy::gain_shield)?;
+        module.function_meta(Enemy::heal)?;
+        module.function_meta(Enemy::heal_allies)?;
+        module.function_meta(Enemy::destroy_nearby_item)?;
+        module.function_meta(Enemy::corrupt_nearby_item)?;
+        module.function_meta(Enemy::spawn_minion)?;
+        module.function_meta(Enemy::rng_float)?;
+
+        Ok(module)
+    }
+
+    #[derive(Clone, rune::Any)]
+    pub struct Enemy {
+        model_state: Rc<RefCell<ModelState>>,
+        side_effects: Rc<RefCell<Vec<Effect>>>,
+        entity_id: Id,
+        entity: Entity,
+
+        #[rune(get)]
+        name: String,
+        #[rune(get)]
+        position: Position,
+        #[rune(get)]
+        health: Hp,
+        #[rune(get)]
+        max_health: Hp,
+        #[rune(get)]
+        shield: Hp,
+    }
+
+    impl Enemy {
+        pub fn from_real(
+            model_state: Rc<RefCell<ModelState>>,
+            side_effects: Rc<RefCell<Vec<Effect>>>,
+            entity_id: Id,
+            entity: &Entity,
+        ) -> Self {
+            let name = match &entity.kind {
+                EntityKind::Enemy(name) => name.to_string(),
+                EntityKind::Player => "Player".to_string(),
+            };
+            Self {
+                model_state,
+                side_effects,
+                entity_id,
+                entity: entity.clone(),
+
+                name,
+                position: entity.position.into(),
+                health: entity.health.value(),
+                max_health: entity.health.max(),
+                shield: entity.shield,
+            }
+        }
+
+        pub fn as_script(&self) -> ScriptEnemy<'_> {
+            ScriptEnemy {
+                model: self.model_state.borrow(),
+                effects: self.side_effects.clone(),
+                entity_id: self.entity_id,
+                entity: &self.entity,
+            }
+        }
+
+        #[rune::function]
+        fn player_position(&self) -> Option<Position> {
+            self.as_script().player_position().map(Position::from)
+        }
+
+        #[rune::function]
+        fn step_towards_player(&self) {
+            self.as_script().step_towards_player()
+        }
+
+        #[rune::function]
+        fn move_to(&self, position: Position) {
+            self.as_script().move_to(position.into())
+        }
+
+        #[rune::function]
+        fn gain_shield(&self, amount: Hp) {
+            self.as_script().shield(amount)
+        }
+
+        #[rune::function]
+        fn heal(&self, amount: Hp) {
+            self.as_script().heal(amount)
+        }
+
+        #[rune::function]
+        fn heal_allies(&self, range: Coord, amount: Hp) {
+            self.as_script().heal_allies(range, amount)
+        }
+
+        #[rune::function]
+        fn destroy_nearby_item(&self, range: Coord) -> bool {
+            self.as_script().destroy_nearby_item(range)
+        }
+
+        #[rune::function]
+        fn corrupt_nearby_item(&self, range: Coord, amount: Hp) -> bool {
+            self.as_script().corrupt_nearby_item(range, amount)
+        }
+
+        #[rune::function]
+        fn spawn_minion(&self, name: &str) -> bool {
+            self.as_script().spawn_minion(name)
+        }
+
+        #[rune::function]
+        fn rng_float(&self) -> f32 {
+            self.as_script().rng_float()
+        }
+    }
+}
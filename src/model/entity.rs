@@ -8,18 +8,32 @@ pub struct Entity {
     pub position: vec2<Coord>,
     pub fraction: Fraction,
     pub health: Health,
+    /// Absorbs the damage before the health.
+    pub shield: Hp,
     pub look_dir: vec2<Coord>,
     pub kind: EntityKind,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, rune::Any)]
 pub enum Fraction {
+    #[rune(constructor)]
     Player,
+    #[rune(constructor)]
     Enemy,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum EntityKind {
     Player,
-    Dummy,
+    /// Name of the enemy kind.
+    Enemy(Rc<str>),
+}
+
+impl Entity {
+    /// Deal damage, the shield takes the hit first.
+    pub fn damage(&mut self, damage: Hp) {
+        let absorbed = damage.clamp(0, self.shield);
+        self.shield -= absorbed;
+        self.health.change(-(damage - absorbed));
+    }
 }
@@ -0,0 +1,99 @@
+use super::*;
+
+/// A non-fatal error in the game logic, most likely caused by a broken item script.
+/// The run continues, but the player is notified.
+#[derive(Debug, Clone)]
+pub enum ModelError {
+    /// The item could not be initialized.
+    ItemInit {
+        item: Rc<str>,
+        message: String,
+    },
+    /// The item script failed while running.
+    Script {
+        item: Rc<str>,
+        message: String,
+    },
+    /// The enemy script failed while running.
+    EnemyScript {
+        enemy: Rc<str>,
+        message: String,
+    },
+    /// The item was triggered too deep in a chain of effects (e.g. items using each other).
+    EffectChain {
+        item: Rc<str>,
+        depth: usize,
+    },
+    /// The player cannot afford to buy an item or reroll the shop.
+    NotEnoughGold {
+        price: Gold,
+        gold: Gold,
+    },
+    /// The selected item is not among the options.
+    InvalidSelection {
+        index: usize,
+        options: usize,
+    },
+    EntityNotFound(Id),
+}
+
+impl ModelError {
+    pub fn item_init(item: &ItemKind, error: impl fmt::Debug) -> Self {
+        Self::ItemInit {
+            item: Rc::clone(&item.config.name),
+            message: format!("{:?}", error),
+        }
+    }
+
+    pub fn script(item: &ItemKind, error: impl fmt::Debug) -> Self {
+        Self::Script {
+            item: Rc::clone(&item.config.name),
+            message: format!("{:?}", error),
+        }
+    }
+
+    pub fn enemy_script(enemy: &EnemyKind, error: impl fmt::Debug) -> Self {
+        Self::EnemyScript {
+            enemy: Rc::clone(&enemy.config.name),
+            message: format!("{:?}", error),
+        }
+    }
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ItemInit { item, message } => {
+                write!(f, "Failed to initialize item {}: {}", item, message)
+            }
+            Self::Script { item, message } => write!(f, "Item {} failed: {}", item, message),
+            Self::EnemyScript { enemy, message } => {
+                write!(f, "Enemy {} failed: {}", enemy, message)
+            }
+            Self::EffectChain { item, depth } => write!(
+                f,
+                "Item {} was triggered more than {} effects deep, the chain is cut off",
+                item, depth
+            ),
+            Self::NotEnoughGold { price, gold } => {
+                write!(f, "Not enough gold: costs {}, but you have {}", price, gold)
+            }
+            Self::InvalidSelection { index, options } => write!(
+                f,
+                "Selected option {}, but there are only {} options",
+                index + 1,
+                options
+            ),
+            Self::EntityNotFound(id) => write!(f, "Entity {:?} does not exist", id),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+/// Log the error and pass it on to the player.
+/// Takes the events instead of the [Model] so it can be used while the state is borrowed.
+pub(super) fn report_error(events: &mut Vec<ModelEvent>, error: ModelError) {
+    log::error!("{}", error);
+    events.push(ModelEvent::Error(error));
+}
@@ -0,0 +1,20 @@
+use super::*;
+
+/// An event produced by the model for the outside world to react to.
+#[derive(Debug, Clone)]
+pub enum ModelEvent {
+    PlaySound(SoundEvent),
+    /// The player's turn has started, the run can be saved.
+    Checkpoint(Box<SavedRun>),
+    /// The run is over and cannot be continued.
+    GameOver,
+    /// Something went wrong, but the run can go on.
+    Error(ModelError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundEvent {
+    Step,
+    Damage,
+    EnemyDeath,
+}
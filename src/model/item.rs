@@ -1,11 +1,17 @@
 use super::*;
 
+use super::engine::item::Position;
+
+use std::collections::BTreeMap;
+
 #[derive(Debug, Clone)]
 pub struct BoardItem {
     pub position: vec2<Coord>,
     pub item_id: Id,
     /// Whether the item was used this turn.
     pub used: bool,
+    /// Number of shifts the item stays in place for.
+    pub anchored: usize,
 }
 
 #[derive(Clone)]
@@ -15,10 +21,12 @@ pub struct InventoryItem {
     /// The id of the board item, if it is present on the board.
     pub on_board: Option<Id>, // TODO: newtype BoardId
     pub kind: ItemKind,
+    /// Starts at 1 and goes up when the copies are merged.
+    pub tier: usize,
     /// The number of turns this item has been present on the board so far.
     pub turns_on_board: usize,
-    /// State of the item script (local variables).
-    pub state: ScriptState,
+    /// Values the item script remembers between calls.
+    pub memory: ItemMemory,
     /// Base stats that might be used to reset all modifications.
     pub base_stats: ItemStats,
     /// Permanent stats that persist through turns.
@@ -38,40 +46,93 @@ pub struct ScriptItem<'a> {
     pub item: &'a InventoryItem,
 }
 
-#[derive(Debug, Default)]
-pub struct ScriptState {
-    pub stack: rune::runtime::Stack,
-}
-
-impl Clone for ScriptState {
-    fn clone(&self) -> Self {
-        Self {
-            stack: rune::alloc::prelude::TryClone::try_clone(&self.stack)
-                .expect("failed to clone item script state"),
-        }
-    }
-}
+/// Named values the item script keeps between calls, e.g. the charges left.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ItemMemory(pub BTreeMap<String, i64>);
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ItemStats {
     pub damage: Option<i64>,
+    /// Distance (in tiles) the item reaches.
+    pub range: Option<i64>,
+    /// Number of activations left, every activation spends one.
+    /// The item cannot be activated once they run out.
+    pub charges: Option<i64>,
+    pub heal: Option<i64>,
+    /// Radius of the light emitted by the item.
+    pub light: Option<i64>,
+    /// Additional moves for the player.
+    pub moves: Option<i64>,
 }
 
-/// A filter for item kind or category of items.
-#[derive(Debug, Clone, rune::Any)]
+/// A single stat of [ItemStats].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, rune::Any)]
+pub enum Stat {
+    #[rune(constructor)]
+    Damage,
+    #[rune(constructor)]
+    Range,
+    #[rune(constructor)]
+    Charges,
+    #[rune(constructor)]
+    Heal,
+    #[rune(constructor)]
+    Light,
+    #[rune(constructor)]
+    Moves,
+}
+
+/// A filter for items by their kind, stats, or whether they are on the board.
+#[derive(Debug, Clone)]
 pub enum ItemFilter {
     Category(Category),
     Named(Rc<str>),
+    Tag(Rc<str>),
+    /// Every one of the filters matches.
+    All(Vec<ItemFilter>),
+    /// At least one of the filters matches.
+    Any(Vec<ItemFilter>),
+    Not(Box<ItemFilter>),
+    /// The item has the stat, even if it is zero.
+    HasStat(Stat),
+    OnBoard,
+    /// In the inventory, but not on the board.
+    InInventory,
 }
 
-/// A filter for item kind or category of items.
+/// Which enemies an item hits.
 #[derive(Debug, Clone, rune::Any)]
 pub enum Target {
     #[rune(constructor)]
     Nearest,
     #[rune(constructor)]
+    Farthest,
+    /// Lowest health (including the shield).
+    #[rune(constructor)]
+    Weakest,
+    /// Highest health (including the shield).
+    #[rune(constructor)]
+    Strongest,
+    #[rune(constructor)]
     Random,
+    /// The first enemy in the direction (diagonals included).
+    #[rune(constructor)]
+    InLine(#[rune(get)] Position),
+    #[rune(constructor)]
+    AtPosition(#[rune(get)] Position),
+    /// Every enemy in the same row as the item.
+    #[rune(constructor)]
+    AllInRow,
+    /// Every enemy in the same column as the item.
+    #[rune(constructor)]
+    AllInColumn,
+    /// The nearest enemy on an observed tile.
+    #[rune(constructor)]
+    Observed,
+    /// The nearest enemy on a tile that is not observed.
+    #[rune(constructor)]
+    Unobserved,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, rune::Any)]
@@ -94,6 +155,40 @@ pub struct ItemKind {
     pub script: Arc<Script>,
 }
 
+impl ItemMemory {
+    pub fn get(&self, key: &str) -> Option<i64> {
+        self.0.get(key).copied()
+    }
+
+    pub fn set(&mut self, key: &str, value: i64) {
+        self.0.insert(key.to_owned(), value);
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<i64> {
+        self.0.remove(key)
+    }
+
+    /// Copy all values from the other memory, overwriting the existing ones.
+    pub fn extend(&mut self, other: &ItemMemory) {
+        self.0
+            .extend(other.0.iter().map(|(key, &value)| (key.clone(), value)));
+    }
+}
+
+impl BoardItem {
+    /// Whether the item is kept in place by the shift:
+    /// by its script, its config, or by standing on a lit tile.
+    pub fn is_anchored(&self, state: &ModelState) -> bool {
+        self.anchored > 0
+            || state.grid.lights.contains_key(&self.position)
+            || state
+                .player
+                .items
+                .get(self.item_id)
+                .map_or(false, |item| item.kind.config.anchored)
+    }
+}
+
 impl InventoryItem {
     pub fn current_stats(&self) -> ItemStats {
         self.base_stats
@@ -102,10 +197,37 @@ impl InventoryItem {
 }
 
 impl ItemFilter {
-    pub fn check(&self, item: &ItemKind) -> bool {
+    /// Check an item the player has.
+    pub fn check(&self, item: &InventoryItem) -> bool {
+        self.matches(
+            &item.kind,
+            &item.current_stats(),
+            Some(item.on_board.is_some()),
+        )
+    }
+
+    /// Check a kind of item the player does not have yet:
+    /// the stats are the base ones, and it is neither on the board nor in the inventory.
+    pub fn check_kind(&self, kind: &ItemKind) -> bool {
+        self.matches(kind, &kind.config.base_stats, None)
+    }
+
+    /// `on_board` is `None` for the items the player does not have.
+    fn matches(&self, kind: &ItemKind, stats: &ItemStats, on_board: Option<bool>) -> bool {
         match self {
-            ItemFilter::Category(category) => item.config.categories.contains(category),
-            ItemFilter::Named(name) => item.config.name == *name,
+            ItemFilter::Category(category) => kind.config.categories.contains(category),
+            ItemFilter::Named(name) => kind.config.name == *name,
+            ItemFilter::Tag(tag) => kind.config.tags.contains(tag),
+            ItemFilter::All(filters) => filters
+                .iter()
+                .all(|filter| filter.matches(kind, stats, on_board)),
+            ItemFilter::Any(filters) => filters
+                .iter()
+                .any(|filter| filter.matches(kind, stats, on_board)),
+            ItemFilter::Not(filter) => !filter.matches(kind, stats, on_board),
+            ItemFilter::HasStat(stat) => stats.get(*stat).is_some(),
+            ItemFilter::OnBoard => on_board == Some(true),
+            ItemFilter::InInventory => on_board == Some(false),
         }
     }
 }
@@ -124,6 +246,77 @@ impl ItemStats {
 
         Self {
             damage: combine(self.damage, other.damage),
+            range: combine(self.range, other.range),
+            charges: combine(self.charges, other.charges),
+            heal: combine(self.heal, other.heal),
+            light: combine(self.light, other.light),
+            moves: combine(self.moves, other.moves),
+        }
+    }
+
+    /// Keep only the stats that the other one has.
+    pub fn restricted_to(&self, other: &Self) -> Self {
+        let restrict = |value: Option<i64>, other: Option<i64>| other.and(value);
+        Self {
+            damage: restrict(self.damage, other.damage),
+            range: restrict(self.range, other.range),
+            charges: restrict(self.charges, other.charges),
+            heal: restrict(self.heal, other.heal),
+            light: restrict(self.light, other.light),
+            moves: restrict(self.moves, other.moves),
+        }
+    }
+
+    pub fn get(&self, stat: Stat) -> Option<i64> {
+        match stat {
+            Stat::Damage => self.damage,
+            Stat::Range => self.range,
+            Stat::Charges => self.charges,
+            Stat::Heal => self.heal,
+            Stat::Light => self.light,
+            Stat::Moves => self.moves,
+        }
+    }
+
+    /// Iterate over the stats the item has.
+    pub fn iter(&self) -> impl Iterator<Item = (Stat, i64)> + '_ {
+        Stat::ALL
+            .into_iter()
+            .filter_map(|stat| self.get(stat).map(|value| (stat, value)))
+    }
+}
+
+impl Stat {
+    pub const ALL: [Stat; 6] = [
+        Stat::Damage,
+        Stat::Range,
+        Stat::Charges,
+        Stat::Heal,
+        Stat::Light,
+        Stat::Moves,
+    ];
+
+    /// The name used in the item descriptions as `{name}`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stat::Damage => "damage",
+            Stat::Range => "range",
+            Stat::Charges => "charges",
+            Stat::Heal => "heal",
+            Stat::Light => "light",
+            Stat::Moves => "moves",
+        }
+    }
+
+    /// A short label to display next to the value.
+    pub fn short_name(&self) -> &'static str {
+        match self {
+            Stat::Damage => "dmg",
+            Stat::Range => "rng",
+            Stat::Charges => "use",
+            Stat::Heal => "hp",
+            Stat::Light => "lgt",
+            Stat::Moves => "mov",
         }
     }
 }
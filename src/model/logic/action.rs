@@ -2,35 +2,65 @@ use super::*;
 
 impl Model {
     pub fn player_action(&mut self, player_input: PlayerInput) {
-        if let PlayerInput::Vision { .. } = player_input {
+        if let PlayerInput::Vision { commit: false, .. } = player_input {
+            // Previewing the vision does not affect the game, so it is not recorded
         } else {
             log::debug!(
                 "Player action: {:?}, current phase: {:?}",
                 player_input,
                 self.phase
             );
+            self.replay.inputs.push(ReplayInput {
+                tick: self.ticks,
+                input: player_input.clone(),
+            });
         }
         match &self.phase {
-            Phase::Player if self.wait_for_effects() => self.player_move(player_input),
+            Phase::Player if self.wait_for_effects() => {
+                if let PlayerInput::DiscardItem(item_id) = player_input {
+                    self.discard_item(item_id);
+                } else {
+                    self.player_move(player_input);
+                }
+            }
             Phase::Vision => self.player_vision(player_input),
             Phase::Map { .. } => self.map_action(player_input),
             Phase::Portal { .. } => self.portal_action(player_input),
+            Phase::Discard { .. } => self.discard_action(player_input),
+            // Let the items finish reacting to the shift first
+            Phase::Placement { .. } if self.wait_for_effects() => {
+                self.placement_action(player_input)
+            }
             Phase::Select {
                 options,
                 extra_items,
             } => match player_input {
-                PlayerInput::SelectItem(i) => self.select_item(options[i].clone()),
+                PlayerInput::SelectItem(i) => match options.get(i).cloned() {
+                    Some(item) => self.select_item(item),
+                    None => {
+                        let options = options.len();
+                        report_error(
+                            &mut self.events,
+                            ModelError::InvalidSelection { index: i, options },
+                        );
+                    }
+                },
                 PlayerInput::Skip => {
                     self.select_phase(0);
-                    self.assets.sounds.step.play();
+                    self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
                 }
                 PlayerInput::Reroll => {
-                    let mut state = self.state.borrow_mut();
-                    if state.player.refreshes > 0 {
-                        state.player.refreshes -= 1;
-                        drop(state);
+                    let extra_items = *extra_items;
+                    if self.spend_gold(self.config.economy.reroll_price) {
                         self.select_phase(extra_items + 1);
-                        self.assets.sounds.step.play();
+                        self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
+                    }
+                }
+                PlayerInput::SellItem(item_id) => self.sell_item(item_id),
+                PlayerInput::DiscardItem(item_id) => self.discard_item(item_id),
+                PlayerInput::MergeItem(item_id) => {
+                    if self.merge_item(item_id) {
+                        self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
                     }
                 }
                 _ => {
@@ -40,7 +70,7 @@ impl Model {
             Phase::GameOver => {
                 if let PlayerInput::Retry = player_input {
                     self.retry();
-                    self.assets.sounds.step.play();
+                    self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
                 }
             }
             _ => {}
@@ -87,12 +117,70 @@ impl Model {
                     self.phase = *next_phase;
                 }
             }
-            self.assets.sounds.step.play();
+            self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
         } else {
             log::error!("tried map action but not in a map phase");
         }
     }
 
+    /// Move the items on the board.
+    /// When done, the rest are placed randomly and the level starts.
+    fn placement_action(&mut self, player_input: PlayerInput) {
+        match player_input {
+            PlayerInput::Skip => {
+                let mut phase = Phase::Vision;
+                std::mem::swap(&mut self.phase, &mut phase);
+                let Phase::Placement {
+                    first_level,
+                    placed,
+                } = phase
+                else {
+                    unreachable!()
+                };
+                log::debug!("Placed {} items manually", placed.len());
+                self.resolve_some(Trigger::Placed, placed);
+                self.spawn_items();
+                self.start_level(first_level);
+                self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
+            }
+            PlayerInput::PlaceItem { item, position } => {
+                if !self.calculate_empty_space().contains(&position) {
+                    log::error!("position {} is not valid, select an empty tile", position);
+                    return;
+                }
+
+                let mut state = self.state.borrow_mut();
+                let state = &mut *state;
+                let Some(inventory_item) = state.player.items.get_mut(item) else {
+                    log::error!("item {:?} is not in the inventory", item);
+                    return;
+                };
+                match inventory_item
+                    .on_board
+                    .and_then(|id| state.items.get_mut(id))
+                {
+                    Some(board_item) => board_item.position = position,
+                    None => {
+                        let on_board = state.items.insert(BoardItem {
+                            position,
+                            item_id: item,
+                            used: false,
+                            anchored: 0,
+                        });
+                        inventory_item.on_board = Some(on_board);
+                        if let Phase::Placement { placed, .. } = &mut self.phase {
+                            placed.push(on_board);
+                        }
+                    }
+                }
+                self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
+            }
+            _ => {
+                log::error!("invalid input during phase Placement, expected an item placement")
+            }
+        }
+    }
+
     /// Swap position with a magic item.
     fn portal_action(&mut self, player_input: PlayerInput) {
         if let PlayerInput::Skip = player_input {
@@ -131,7 +219,7 @@ impl Model {
                 .find(|(_, item)| item.position == target_pos)
             {
                 let item = &state_ref.player.items[target.item_id];
-                if ItemFilter::Category(Category::Magic).check(&item.kind) {
+                if ItemFilter::Category(Category::Magic).check(item) {
                     let Some((player_id, player)) = state_ref
                         .entities
                         .iter_mut()
@@ -164,7 +252,7 @@ impl Model {
                         self.phase = *next_phase;
                     }
 
-                    self.assets.sounds.step.play();
+                    self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
                 } else {
                     log::error!(
                         "invalid input during phase Portal, expected a magic item position, found a non-magic item"
@@ -192,7 +280,7 @@ impl Model {
             log::debug!("Skipping turn");
             drop(state);
             self.vision_phase();
-            self.assets.sounds.step.play();
+            self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
             return;
         }
 
@@ -245,24 +333,92 @@ impl Model {
         if moved {
             let mut state = self.state.borrow_mut();
             state.player.moves_left = state.player.moves_left.saturating_sub(1);
-            self.assets.sounds.step.play();
+            self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
+        }
+    }
+
+    /// Take the gold from the player, or report that there is not enough.
+    /// Returns `false` if the player cannot afford it.
+    fn spend_gold(&mut self, price: Gold) -> bool {
+        let mut state = self.state.borrow_mut();
+        let gold = state.player.gold;
+        if gold < price {
+            drop(state);
+            report_error(&mut self.events, ModelError::NotEnoughGold { price, gold });
+            return false;
+        }
+        state.player.gold -= price;
+        true
+    }
+
+    /// Remove the item from the inventory and the board.
+    /// Unlike destruction, this does not trigger the item.
+    fn take_item(&mut self, item_id: Id) -> Option<InventoryItem> {
+        let mut state = self.state.borrow_mut();
+        let Some(item) = state.player.items.remove(item_id) else {
+            log::error!("item {:?} is not in the inventory", item_id);
+            return None;
+        };
+        if let Some(id) = item.on_board {
+            state.items.remove(id);
+        }
+        Some(item)
+    }
+
+    /// Remove the item from the inventory (and the board) for half its price.
+    fn sell_item(&mut self, item_id: Id) {
+        let Some(item) = self.take_item(item_id) else {
+            return;
+        };
+        let price = item.kind.config.shop.sell_price();
+        self.state.borrow_mut().player.gold += price;
+        log::debug!("Sold item {:?} for {}", item.kind.config.name, price);
+        self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
+    }
+
+    fn discard_item(&mut self, item_id: Id) {
+        if let Some(item) = self.take_item(item_id) {
+            log::debug!("Discarded item {:?}", item.kind.config.name);
+            self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
+        }
+    }
+
+    /// Drop items until the inventory is back within the capacity.
+    fn discard_action(&mut self, player_input: PlayerInput) {
+        let PlayerInput::DiscardItem(item_id) = player_input else {
+            log::error!("invalid input during phase Discard, expected an item to discard");
+            return;
+        };
+        self.discard_item(item_id);
+
+        if self.state.borrow().player.items.len() <= self.config.inventory_capacity {
+            let mut phase = Phase::Vision;
+            std::mem::swap(&mut self.phase, &mut phase);
+            if let Phase::Discard { next_phase } = phase {
+                log::debug!("Moving from Discard phase to {:?}", next_phase);
+                self.phase = *next_phase;
+            }
         }
     }
 
     fn select_item(&mut self, item: ItemKind) {
         log::debug!("Select item {:?}", item);
-        let item = self
-            .engine
-            .init_item(item)
-            .expect("Item initialization failed"); // TODO: handle error
-        self.state.borrow_mut().player.items.insert(item);
+        if !self.spend_gold(item.config.shop.price) {
+            return;
+        }
+        match self.engine.init_item(item.clone()) {
+            Ok(item) => {
+                self.acquire_item(item);
+            }
+            Err(err) => report_error(&mut self.events, ModelError::item_init(&item, err)),
+        }
         let items = if let Phase::Select { extra_items, .. } = self.phase {
             extra_items
         } else {
             0
         };
         self.select_phase(items);
-        self.assets.sounds.step.play();
+        self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
     }
 
     fn player_vision(&mut self, player_input: PlayerInput) {
@@ -293,7 +449,9 @@ impl Model {
 
         self.update_vision();
         if let PlayerInput::Vision { commit: true, .. } = player_input {
-            self.assets.sounds.step.play();
+            let observed = self.observed_items();
+            self.resolve_some(Trigger::Observed, observed);
+            self.events.push(ModelEvent::PlaySound(SoundEvent::Step));
             self.phase = Phase::PostVision {
                 timer: Lifetime::new_max(r32(1.0)),
             };
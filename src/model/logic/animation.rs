@@ -53,25 +53,44 @@ impl Model {
                     }
                 }
                 AnimationKind::ItemEffect { .. } => {}
-                AnimationKind::EntityDeath { entity, .. } => {
-                    self.state.borrow_mut().entities.remove(*entity);
-                    self.assets.sounds.enemy_death.play();
-                }
-                AnimationKind::ItemDeath { item, .. } => {
-                    let mut state = self.state.borrow_mut();
-                    if let Some(item) = state.player.items.remove(*item) {
-                        if let Some(id) = item.on_board {
-                            state.items.remove(id);
+                AnimationKind::EntityDeath {
+                    entity: entity_id, ..
+                } => {
+                    let entity = self.state.borrow_mut().entities.remove(*entity_id);
+                    if let Some(entity) = entity {
+                        self.events
+                            .push(ModelEvent::PlaySound(SoundEvent::EnemyDeath));
+                        self.resolve_enemy(*entity_id, &entity, EnemyTrigger::Death);
+                        if entity.fraction == Fraction::Enemy {
+                            self.state.borrow_mut().player.gold +=
+                                self.config.economy.gold_per_kill;
+                            self.resolve_all(Trigger::EnemyKilled);
                         }
                     }
                 }
-                AnimationKind::Dupe { kind } => {
-                    self.new_item_and_spawn(kind.clone());
+                AnimationKind::ItemDeath { item, .. } => {
+                    self.destroy_item(*item);
+                }
+                AnimationKind::Dupe { kind, memory } => {
+                    if let Err(err) = self.new_item_and_spawn(kind.clone(), memory.clone()) {
+                        report_error(&mut self.events, err);
+                    }
                 }
                 AnimationKind::Damage { target, damage, .. } => {
-                    if let Some(target) = self.state.borrow_mut().entities.get_mut(*target) {
-                        target.health.change(-damage);
-                        self.assets.sounds.damage.play();
+                    let entity = self
+                        .state
+                        .borrow_mut()
+                        .entities
+                        .get_mut(*target)
+                        .map(|entity| {
+                            entity.damage(*damage);
+                            entity.clone()
+                        });
+                    if let Some(entity) = entity {
+                        self.events.push(ModelEvent::PlaySound(SoundEvent::Damage));
+                        if *damage > 0 && !entity.health.is_min() {
+                            self.resolve_enemy(*target, &entity, EnemyTrigger::Damaged);
+                        }
                     }
                 }
                 AnimationKind::Bonus {
@@ -99,27 +118,36 @@ impl Model {
         }
     }
 
-    fn new_item_and_spawn(&mut self, kind: ItemKind) {
-        let item = self
+    fn new_item_and_spawn(
+        &mut self,
+        kind: ItemKind,
+        memory: Option<ItemMemory>,
+    ) -> Result<(), ModelError> {
+        let mut item = self
             .engine
-            .init_item(kind)
-            .expect("Item initialization failed"); // TODO: handle error
-        let item_id = self.state.borrow_mut().player.items.insert(item);
+            .init_item(kind.clone())
+            .map_err(|err| ModelError::item_init(&kind, err))?;
+        if let Some(memory) = memory {
+            item.memory = memory;
+        }
+        let item_id = self.acquire_item(item);
 
         let available = self
             .calculate_empty_space()
             .sub(&self.state.borrow().visible_tiles);
-        if !available.is_empty() {
-            let mut rng = thread_rng();
-            let &position = available.iter().choose(&mut rng).unwrap();
-
-            let mut state = self.state.borrow_mut();
+        let mut state = self.state.borrow_mut();
+        let position = choose_position(&available, state.rng.get_mut());
+        if let Some(position) = position {
             let on_board = state.items.insert(BoardItem {
                 position,
                 item_id,
                 used: false,
+                anchored: 0,
             });
             state.player.items[item_id].on_board = Some(on_board);
+            drop(state);
+            self.resolve_some(Trigger::Placed, vec![on_board]);
         }
+        Ok(())
     }
 }
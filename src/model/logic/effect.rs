@@ -9,12 +9,20 @@ impl Model {
                 break;
             }
         }
+        if self.effect_queue_stack.is_empty() && self.resolution_queue.is_empty() {
+            // The chain is over
+            self.chain_depth = 0;
+        }
 
         // if self.wait_for_animations() {
         if self.animations.is_empty() {
             if self.effect_queue_stack.is_empty() {
-                if let Some((item, trigger)) = self.resolution_queue.pop_front() {
+                if let Some((item, trigger, depth)) = self.resolution_queue.pop_front() {
+                    self.chain_depth = depth;
                     self.resolve_trigger(trigger, item);
+                } else if let Some((entity, trigger)) = self.enemy_queue.pop_front() {
+                    self.chain_depth = 0;
+                    self.resolve_queued_enemy(entity, trigger);
                 }
             } else {
                 self.resolve_next_effect();
@@ -31,9 +39,12 @@ impl Model {
     }
 
     pub fn resolve_effect(&mut self, effect: QueuedEffect) {
+        let Some(effect) = self.react_to_effect(effect) else {
+            return;
+        };
         log::debug!("Resolving effect {:?}", effect);
         let mut state = self.state.borrow_mut();
-        let proc_item = state.items.get(effect.proc_item);
+        let proc_item = effect.proc_item.and_then(|id| state.items.get(id));
         let stats = proc_item.map(|item| {
             let stats = state.player.items[item.item_id].current_stats();
             crate::model::engine::item::Stats::from(stats)
@@ -41,18 +52,19 @@ impl Model {
 
         let mut animations = Vec::new();
 
-        let animation_delay = self.animations.insert(Animation::new(
-            self.config.animation_time,
-            AnimationKind::ItemEffect {
-                item: effect.proc_item,
-            },
-        ));
-        animations.push(animation_delay);
+        // The effects of the enemies have no item to animate
+        let animation_delay = effect.proc_item.map(|item| {
+            self.animations.insert(Animation::new(
+                self.config.animation_time,
+                AnimationKind::ItemEffect { item },
+            ))
+        });
+        animations.extend(animation_delay);
 
         let mut play_animation =
             |kind| {
                 animations.push(self.animations.insert(
-                    Animation::new(self.config.animation_time, kind).after([animation_delay]),
+                    Animation::new(self.config.animation_time, kind).after(animation_delay),
                 ));
             };
 
@@ -62,14 +74,27 @@ impl Model {
                     item.used = true;
                 }
             }
-            Effect::Damage { target, damage } => {
+            Effect::Damage {
+                target,
+                damage,
+                extra,
+            } => {
                 let Some(proc_item) = proc_item else {
                     log::error!("proc item not found for the damage effect");
                     return;
                 };
-                let damage: Hp = damage
-                    .call((stats.unwrap(),))
-                    .expect("failed to call rune function"); // TODO: handle error
+                let damage: Hp = match self
+                    .engine
+                    .with_budget(|| damage.call((stats.unwrap(),)))
+                    .into_result()
+                {
+                    Ok(damage) => damage + extra,
+                    Err(err) => {
+                        let item = &state.player.items[proc_item.item_id].kind;
+                        report_error(&mut self.events, ModelError::script(item, err));
+                        0
+                    }
+                };
                 play_animation(AnimationKind::Damage {
                     from: proc_item.position,
                     target,
@@ -115,9 +140,15 @@ impl Model {
                 if let Some(inv) = state.player.items.get(item_id) {
                     play_animation(AnimationKind::Dupe {
                         kind: inv.kind.clone(),
+                        memory: inv.kind.config.memory.duplicate.then(|| inv.memory.clone()),
                     });
                 }
             }
+            Effect::Anchor { item, shifts } => {
+                if let Some(board_item) = state.items.get_mut(item) {
+                    board_item.anchored = board_item.anchored.max(shifts);
+                }
+            }
             Effect::GainMoves { moves } => {
                 state.player.moves_left += moves;
             }
@@ -172,15 +203,27 @@ impl Model {
                         .iter()
                         .find(|kind| *kind.config.name == target_name)
                     {
-                        let new_item = self
-                            .engine
-                            .init_item(target.clone())
-                            .expect("Item initialization failed");
+                        let new_item = match self.engine.init_item(target.clone()) {
+                            Ok(item) => item,
+                            Err(err) => {
+                                report_error(&mut self.events, ModelError::item_init(target, err));
+                                return;
+                            }
+                        };
                         let on_board = item.on_board;
                         let turns = item.turns_on_board;
+                        let memory = item
+                            .kind
+                            .config
+                            .memory
+                            .transform
+                            .then(|| std::mem::take(&mut item.memory));
                         *item = new_item;
                         item.on_board = on_board;
                         item.turns_on_board = turns;
+                        if let Some(memory) = memory {
+                            item.memory.extend(&memory);
+                        }
                     } else {
                         log::error!(
                             "Tried transforming an item into an unknown kind: {:?}",
@@ -196,7 +239,14 @@ impl Model {
             } => {
                 state.grid.light_up(position, radius, duration);
                 drop(state);
+                let before: HashSet<Id> = self.observed_items().into_iter().collect();
                 self.update_vision();
+                let lit = self
+                    .observed_items()
+                    .into_iter()
+                    .filter(|id| !before.contains(id))
+                    .collect();
+                self.resolve_some(Trigger::Observed, lit);
             }
             Effect::UseItem { item } => {
                 drop(state);
@@ -205,13 +255,32 @@ impl Model {
             Effect::NewItem { kind } => {
                 log::info!("new item {:?}", kind);
                 drop(state);
-                if let Ok(item) = self.engine.init_item(kind) {
-                    self.state.borrow_mut().player.items.insert(item);
+                match self.engine.init_item(kind.clone()) {
+                    Ok(item) => {
+                        self.acquire_item(item);
+                    }
+                    Err(err) => report_error(&mut self.events, ModelError::item_init(&kind, err)),
                 }
             }
+            Effect::RemoveItem { item_id } => {
+                if let Some(item) = state.player.items.remove(item_id) {
+                    if let Some(id) = item.on_board {
+                        state.items.remove(id);
+                    }
+                }
+            }
+            entity_effect @ (Effect::MoveEntity { .. }
+            | Effect::Shield { .. }
+            | Effect::Heal { .. }
+            | Effect::SpawnEnemy { .. }) => {
+                drop(state);
+                self.resolve_enemy_effect(entity_effect);
+            }
         }
 
-        let board_item = effect.proc_item;
+        let (Some(board_item), Some(animation_delay)) = (effect.proc_item, animation_delay) else {
+            return;
+        };
         if self.resolving_items.get(&board_item).is_none() {
             // Set wind up animation
             let down = self
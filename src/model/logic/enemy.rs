@@ -0,0 +1,301 @@
+use super::*;
+
+// NOTE: expose functions in src/model/engine.rs
+impl ScriptEnemy<'_> {
+    fn effects(&self) -> ScriptEffects {
+        ScriptEffects(self.effects.borrow_mut())
+    }
+
+    /// Whether the position is free and nothing is going to move there.
+    fn is_free(&self, position: vec2<Coord>) -> bool {
+        is_free(&self.model, position)
+            && !self.effects.borrow().iter().any(|effect| match effect {
+                Effect::MoveEntity { target, .. } => *target == position,
+                Effect::SpawnEnemy {
+                    position: target, ..
+                } => *target == position,
+                _ => false,
+            })
+    }
+
+    pub fn player_position(&self) -> Option<vec2<Coord>> {
+        self.model
+            .entities
+            .iter()
+            .find(|(_, entity)| matches!(entity.kind, EntityKind::Player))
+            .map(|(_, entity)| entity.position)
+    }
+
+    pub fn step_towards_player(&mut self) {
+        let Some(player) = self.player_position() else {
+            return;
+        };
+        let from = self.entity.position;
+        if distance_manhattan(from, player) <= 1 {
+            // Already there
+            return;
+        }
+
+        let delta = player - from;
+        let horizontal = vec2(delta.x.signum(), 0);
+        let vertical = vec2(0, delta.y.signum());
+        // Prefer the longer axis
+        let dirs = if delta.x.abs() >= delta.y.abs() {
+            [horizontal, vertical]
+        } else {
+            [vertical, horizontal]
+        };
+        for dir in dirs {
+            let target = from + dir;
+            if dir != vec2::ZERO && self.is_free(target) {
+                self.effects().move_entity(self.entity_id, target);
+                return;
+            }
+        }
+    }
+
+    pub fn move_to(&mut self, target: vec2<Coord>) {
+        if self.is_free(target) {
+            self.effects().move_entity(self.entity_id, target);
+        }
+    }
+
+    pub fn shield(&mut self, amount: Hp) {
+        self.effects().shield(self.entity_id, amount);
+    }
+
+    pub fn heal(&mut self, amount: Hp) {
+        self.effects().heal(self.entity_id, amount);
+    }
+
+    /// Heal other enemies in range.
+    pub fn heal_allies(&mut self, range: Coord, amount: Hp) {
+        for (id, _) in self.model.entities.iter().filter(|&(id, entity)| {
+            id != self.entity_id
+                && entity.fraction == self.entity.fraction
+                && distance(entity.position, self.entity.position) <= range
+        }) {
+            self.effects().heal(id, amount);
+        }
+    }
+
+    /// Choose a random item on the board in range.
+    fn find_item_nearby(
+        &self,
+        range: Coord,
+        filter: impl Fn(&InventoryItem) -> bool,
+    ) -> Option<(Id, &BoardItem)> {
+        let mut rng = self.model.rng.borrow_mut();
+        self.model
+            .items
+            .iter()
+            .filter(|(_, board_item)| {
+                distance(board_item.position, self.entity.position) <= range
+                    && self
+                        .model
+                        .player
+                        .items
+                        .get(board_item.item_id)
+                        .map_or(false, &filter)
+            })
+            .sorted_by_key(|(_, board_item)| (board_item.position.x, board_item.position.y))
+            .choose(&mut *rng)
+    }
+
+    /// Returns `false` if there are no items nearby.
+    pub fn destroy_nearby_item(&mut self, range: Coord) -> bool {
+        let Some(item_id) = self
+            .find_item_nearby(range, |_| true)
+            .map(|(_, board_item)| board_item.item_id)
+        else {
+            return false;
+        };
+        self.effects().destroy(item_id);
+        true
+    }
+
+    /// Permanently lower the damage of a nearby item.
+    /// Returns `false` if there are no items with damage nearby.
+    pub fn corrupt_nearby_item(&mut self, range: Coord, amount: Hp) -> bool {
+        let Some((target, _)) =
+            self.find_item_nearby(range, |item| item.current_stats().damage.is_some())
+        else {
+            return false;
+        };
+        let bonus = ItemStats {
+            damage: Some(-amount),
+            ..Default::default()
+        };
+        self.effects()
+            .bonus(self.entity.position, target, bonus, true);
+        true
+    }
+
+    /// Spawn an enemy next to this one (or in its place, if it is dead).
+    /// Returns `false` if there is no space or the kind is unknown.
+    pub fn spawn_minion(&mut self, name: &str) -> bool {
+        let Some(kind) = self
+            .model
+            .all_enemies
+            .iter()
+            .find(|kind| *kind.config.name == *name)
+        else {
+            log::error!("Tried spawning an unknown enemy: {:?}", name);
+            return false;
+        };
+
+        let from = self.entity.position;
+        let positions: HashSet<_> = [vec2(0, 0), vec2(1, 0), vec2(-1, 0), vec2(0, 1), vec2(0, -1)]
+            .into_iter()
+            .map(|dir| from + dir)
+            .filter(|&pos| self.is_free(pos))
+            .collect();
+        let Some(position) = choose_position(&positions, &mut *self.model.rng.borrow_mut()) else {
+            return false;
+        };
+        self.effects().spawn_enemy(kind.clone(), position);
+        true
+    }
+
+    pub fn rng_float(&self) -> f32 {
+        self.model.rng.borrow_mut().gen()
+    }
+}
+
+impl Model {
+    /// Queue the trigger for all enemies, ordered the same way as items.
+    pub(super) fn resolve_all_enemies(&mut self, trigger: EnemyTrigger) {
+        let state = self.state.borrow();
+        let ids: Vec<Id> = state
+            .entities
+            .iter()
+            .filter(|(_, entity)| matches!(entity.kind, EntityKind::Enemy(_)))
+            .sorted_by_key(|(_, entity)| (entity.position.x, -entity.position.y))
+            .map(|(id, _)| id)
+            .collect();
+        drop(state);
+
+        self.enemy_queue
+            .extend(ids.into_iter().map(|id| (id, trigger)));
+    }
+
+    pub(super) fn resolve_queued_enemy(&mut self, entity_id: Id, trigger: EnemyTrigger) {
+        let entity = self.state.borrow().entities.get(entity_id).cloned();
+        if let Some(entity) = entity {
+            self.resolve_enemy(entity_id, &entity, trigger);
+        }
+    }
+
+    /// Run the enemy's script and apply its effects on the entities right away.
+    /// The effects on the items are queued, so that the items can react to them.
+    /// The entity is passed separately, since it might already be removed (on death).
+    pub(super) fn resolve_enemy(&mut self, entity_id: Id, entity: &Entity, trigger: EnemyTrigger) {
+        let EntityKind::Enemy(name) = &entity.kind else {
+            return;
+        };
+        let Some(kind) = self
+            .state
+            .borrow()
+            .all_enemies
+            .iter()
+            .find(|kind| kind.config.name == *name)
+            .cloned()
+        else {
+            log::error!("Unknown enemy kind {:?}", name);
+            return;
+        };
+
+        let result = self.engine.enemy_trigger(&kind, entity_id, entity, trigger);
+        let effects = std::mem::take(&mut *self.side_effects.borrow_mut());
+        if let Err(err) = result {
+            report_error(&mut self.events, ModelError::enemy_script(&kind, err));
+            return;
+        }
+
+        log::debug!("Enemy {:?} resolved: {:?}", entity_id, effects);
+        let (queued, immediate): (Vec<_>, Vec<_>) =
+            effects.into_iter().partition(PendingEffect::is_reactable);
+        for effect in immediate {
+            self.resolve_enemy_effect(effect);
+        }
+        if !queued.is_empty() {
+            self.effect_queue_stack.push(
+                queued
+                    .into_iter()
+                    .map(|effect| QueuedEffect {
+                        trigger: None,
+                        proc_item: None,
+                        effect,
+                    })
+                    .collect(),
+            );
+        }
+    }
+
+    /// Apply an effect that targets entities.
+    pub(super) fn resolve_enemy_effect(&mut self, effect: Effect) {
+        let mut state = self.state.borrow_mut();
+        let animation_time = self.config.animation_time;
+        match effect {
+            Effect::MoveEntity { entity_id, target } => {
+                let moving_there = self.animations.iter().any(|(_, animation)| {
+                    matches!(
+                        animation.kind,
+                        AnimationKind::MoveEntity { target_pos, .. } if target_pos == target
+                    )
+                });
+                if !is_free(&state, target) || moving_there {
+                    log::debug!("Entity {:?} cannot move to {}", entity_id, target);
+                    return;
+                }
+                self.animations.insert(Animation::new(
+                    animation_time,
+                    AnimationKind::MoveEntity {
+                        entity_id,
+                        target_pos: target,
+                    },
+                ));
+            }
+            Effect::Shield { entity_id, amount } => {
+                if let Some(entity) = state.entities.get_mut(entity_id) {
+                    entity.shield += amount;
+                }
+            }
+            Effect::Heal { entity_id, amount } => {
+                if let Some(entity) = state.entities.get_mut(entity_id) {
+                    entity.health.change(amount);
+                }
+            }
+            Effect::SpawnEnemy { kind, position } => {
+                if !is_free(&state, position) {
+                    log::debug!("Cannot spawn {:?} at {}", kind.config.name, position);
+                    return;
+                }
+                state.entities.insert(Entity {
+                    position,
+                    fraction: Fraction::Enemy,
+                    health: Health::new_max(kind.health(self.level)),
+                    shield: 0,
+                    look_dir: vec2(0, -1),
+                    kind: EntityKind::Enemy(Rc::clone(&kind.config.name)),
+                });
+            }
+            effect => {
+                log::error!("Effect {:?} cannot be produced by an enemy", effect);
+            }
+        }
+    }
+}
+
+/// Whether there are no entities or items at the position.
+fn is_free(state: &ModelState, position: vec2<Coord>) -> bool {
+    state.grid.check_pos(position)
+        && !state
+            .entities
+            .iter()
+            .any(|(_, entity)| entity.position == position)
+        && !state
+            .items
+            .iter()
+            .any(|(_, item)| item.position == position)
+}
@@ -6,7 +6,11 @@ impl ScriptEffects<'_> {
     // }
 
     pub fn damage(&mut self, target: Id, damage: Rc<ScriptFunction>) {
-        self.0.push(Effect::Damage { target, damage });
+        self.0.push(Effect::Damage {
+            target,
+            damage,
+            extra: 0,
+        });
     }
 
     pub fn bonus(&mut self, from: vec2<Coord>, target: Id, bonus: ItemStats, permanent: bool) {
@@ -30,6 +34,10 @@ impl ScriptEffects<'_> {
         self.0.push(Effect::Duplicate { item_id });
     }
 
+    pub fn anchor(&mut self, item: Id, shifts: usize) {
+        self.0.push(Effect::Anchor { item, shifts });
+    }
+
     pub fn gain_moves(&mut self, moves: usize) {
         self.0.push(Effect::GainMoves { moves });
     }
@@ -64,4 +72,20 @@ impl ScriptEffects<'_> {
     pub fn new_item(&mut self, kind: ItemKind) {
         self.0.push(Effect::NewItem { kind });
     }
+
+    pub fn move_entity(&mut self, entity_id: Id, target: vec2<Coord>) {
+        self.0.push(Effect::MoveEntity { entity_id, target });
+    }
+
+    pub fn shield(&mut self, entity_id: Id, amount: Hp) {
+        self.0.push(Effect::Shield { entity_id, amount });
+    }
+
+    pub fn heal(&mut self, entity_id: Id, amount: Hp) {
+        self.0.push(Effect::Heal { entity_id, amount });
+    }
+
+    pub fn spawn_enemy(&mut self, kind: EnemyKind, position: vec2<Coord>) {
+        self.0.push(Effect::SpawnEnemy { kind, position });
+    }
 }
@@ -7,6 +7,19 @@ impl Model {
             if self.level > 0 {
                 self.score += self.config.score_per_level;
                 self.score += self.config.score_per_turn_left * state.player.turns_left as Score;
+
+                let economy = &self.config.economy;
+                let treasures = state
+                    .items
+                    .iter()
+                    .filter(|(_, board_item)| {
+                        ItemFilter::Category(Category::Treasure)
+                            .check(&state.player.items[board_item.item_id])
+                    })
+                    .count();
+                let gold = economy.gold_per_turn_left * state.player.turns_left as Gold
+                    + economy.gold_per_treasure * treasures as Gold;
+                state.player.gold += gold;
             }
 
             self.level += 1;
@@ -19,7 +32,21 @@ impl Model {
         }
 
         self.spawn_enemies();
+        if self.config.manual_placement {
+            log::debug!("Placement phase");
+            self.phase = Phase::Placement {
+                first_level,
+                placed: Vec::new(),
+            };
+            return;
+        }
         self.spawn_items();
+        self.start_level(first_level);
+    }
+
+    /// Called after the items have been placed.
+    pub(super) fn start_level(&mut self, first_level: bool) {
+        self.resolve_all(Trigger::LevelStart);
 
         if first_level {
             self.phase = Phase::LevelStarting {
@@ -30,13 +57,38 @@ impl Model {
         }
     }
 
+    /// Move everything that is not observed or anchored to random positions and trigger the moved items.
     pub(super) fn shift_everything(&mut self) {
         let mut state = self.state.borrow_mut();
-        let available: HashSet<_> = state.grid.tiles.sub(&state.visible_tiles);
+        let anchored: HashSet<Id> = state
+            .items
+            .iter()
+            .filter(|(_, item)| item.is_anchored(&state))
+            .map(|(id, _)| id)
+            .collect();
+        for (_, item) in &mut state.items {
+            item.anchored = item.anchored.saturating_sub(1);
+        }
+
+        let available: HashSet<_> = state
+            .grid
+            .tiles
+            .iter()
+            .filter(|pos| {
+                !state.visible_tiles.contains(*pos)
+                    && !anchored.iter().any(|&id| state.items[id].position == **pos)
+            })
+            .copied()
+            .collect();
         if available.is_empty() {
             // Cannot shift
             return;
         }
+        let before: HashMap<Id, vec2<Coord>> = state
+            .items
+            .iter()
+            .map(|(id, item)| (id, item.position))
+            .collect();
 
         enum Thing {
             Entity(Id),
@@ -46,6 +98,7 @@ impl Model {
         let items = state
             .items
             .iter()
+            .filter(|(i, _)| !anchored.contains(i))
             .map(|(i, item)| (Thing::Item(i), item.position));
         let entities = state
             .entities
@@ -53,11 +106,12 @@ impl Model {
             .map(|(i, e)| (Thing::Entity(i), e.position));
         let things = items.chain(entities);
 
-        let mut rng = thread_rng();
+        let mut rng = state.rng.borrow_mut();
         let moves: Vec<(Thing, vec2<Coord>)> = things
             .filter(|(_, pos)| !state.visible_tiles.contains(pos))
-            .map(|(i, _)| (i, *available.iter().choose(&mut rng).unwrap()))
+            .map(|(i, _)| (i, choose_position(&available, &mut *rng).unwrap()))
             .collect();
+        drop(rng);
 
         for (thing, target) in moves {
             let from = match thing {
@@ -82,6 +136,15 @@ impl Model {
                 Thing::Item(i) => state.items[i].position = target,
             }
         }
+
+        let shifted: Vec<Id> = state
+            .items
+            .iter()
+            .filter(|(id, item)| before.get(id) != Some(&item.position))
+            .map(|(id, _)| id)
+            .collect();
+        drop(state);
+        self.resolve_some(Trigger::Shifted, shifted);
     }
 
     fn spawn_enemies(&mut self) {
@@ -89,26 +152,36 @@ impl Model {
             .calculate_empty_space()
             .sub(&self.state.borrow().visible_tiles);
 
-        let options = [EntityKind::Dummy];
-        let mut rng = thread_rng();
+        let options: Vec<EnemyKind> = self
+            .state
+            .borrow()
+            .all_enemies
+            .iter()
+            .filter(|kind| kind.spawns_on(self.level))
+            .cloned()
+            .collect();
+        if options.is_empty() {
+            log::error!("No enemies can spawn on level {}", self.level);
+            return;
+        }
 
         let level = self.level.saturating_sub(1);
         let enemies = level % 4 + level / 4 + 1;
         let enemies = enemies.min(5);
 
-        let health = 5 * 2_usize.pow(self.level as u32 / 4);
-        let health = health as i64;
-
         for _ in 0..enemies {
             if available.is_empty() {
                 // Replace an existing item
                 let mut state = self.state.borrow_mut();
-                if let Some((i, _)) = state
+                let mut rng = state.rng.borrow_mut();
+                let replace = state
                     .items
                     .iter()
                     .filter(|(_, item)| !state.visible_tiles.contains(&item.position))
-                    .choose(&mut rng)
-                {
+                    .choose(&mut *rng)
+                    .map(|(i, _)| i);
+                drop(rng);
+                if let Some(i) = replace {
                     let item = state.items.remove(i).unwrap();
                     if let Some(item) = state.player.items.get_mut(item.item_id) {
                         item.on_board = None;
@@ -118,17 +191,22 @@ impl Model {
                 }
             }
 
-            let kind = options.choose(&mut rng).unwrap();
-            let Some(&position) = available.iter().choose(&mut rng) else {
+            let state = self.state.borrow();
+            let mut rng = state.rng.borrow_mut();
+            let kind = options.choose(&mut *rng).unwrap();
+            let Some(position) = choose_position(&available, &mut *rng) else {
                 break;
             };
+            drop(rng);
+            drop(state);
 
             self.state.borrow_mut().entities.insert(Entity {
                 position,
                 fraction: Fraction::Enemy,
-                health: Health::new_max(health),
+                health: Health::new_max(kind.health(self.level)),
+                shield: 0,
                 look_dir: vec2(0, -1),
-                kind: kind.clone(),
+                kind: EntityKind::Enemy(Rc::clone(&kind.config.name)),
             });
 
             available.remove(&position);
@@ -143,11 +221,11 @@ impl Model {
             return;
         }
 
-        let mut rng = thread_rng();
-
         // What is this trick KEKW
-        let mut state = self.state.borrow_mut();
-        let state = &mut *state;
+        let mut state_ref = self.state.borrow_mut();
+        let state = &mut *state_ref;
+        let rng = state.rng.get_mut();
+        let mut placed = Vec::new();
 
         for (item_id, item) in &mut state.player.items {
             if let Some(id) = item.on_board {
@@ -160,18 +238,24 @@ impl Model {
                 }
             }
 
-            let position = *available.iter().choose(&mut rng).unwrap();
+            let position = choose_position(&available, rng).unwrap();
             let on_board = state.items.insert(BoardItem {
                 position,
                 item_id,
                 used: false,
+                anchored: 0,
             });
             item.on_board = Some(on_board);
+            placed.push(on_board);
 
             available.remove(&position);
             if available.is_empty() {
+                log::debug!("No space left on the board for the rest of the items");
                 break;
             }
         }
+        drop(state_ref);
+
+        self.resolve_some(Trigger::Placed, placed);
     }
 }
@@ -8,43 +8,139 @@ impl ScriptItem<'_> {
 
     pub fn damage(&mut self, target: Target, damage: ScriptFunction) {
         let damage = Rc::new(damage);
-        let mut rng = thread_rng(); // TODO: seeded rng
+        let targets = self.find_targets(&target);
+        if targets.is_empty() {
+            log::debug!("Item tried attacking but no target was found");
+        }
+        for target in targets {
+            self.effects().damage(target, damage.clone());
+        }
+    }
 
+    /// Whether the entity is hostile to the item.
+    fn is_hostile(&self, entity: &Entity) -> bool {
         let source_fraction = Fraction::Player; // TODO: non-player items?
+        source_fraction != entity.fraction
+    }
 
+    /// Whether the entity can be hit with the target at all, regardless of the other entities.
+    fn fits_target(&self, target: &Target, entity: &Entity) -> bool {
+        let from = self.board_item.position;
+        let pos = entity.position;
+        match target {
+            Target::InLine(dir) => {
+                let dir = vec2(dir.x.signum(), dir.y.signum());
+                let dist = distance(from, pos);
+                dir != vec2::ZERO && dist > 0 && dir * dist == pos - from
+            }
+            Target::AtPosition(target) => pos == vec2::from(*target),
+            Target::AllInRow => pos.y == from.y,
+            Target::AllInColumn => pos.x == from.x,
+            Target::Observed => self.model.visible_tiles.contains(&pos),
+            Target::Unobserved => !self.model.visible_tiles.contains(&pos),
+            Target::Nearest
+            | Target::Farthest
+            | Target::Weakest
+            | Target::Strongest
+            | Target::Random => true,
+        }
+    }
+
+    /// Find the entities hit by the target.
+    /// Ties are broken by the distance to the item, then by the position
+    /// (left to right, top to bottom), so the result only depends on the board and the rng.
+    pub fn find_targets(&self, target: &Target) -> Vec<Id> {
+        let from = self.board_item.position;
+        let candidates: Vec<(Id, &Entity)> = self
+            .model
+            .entities
+            .iter()
+            .filter(|(_, entity)| self.is_hostile(entity) && self.fits_target(target, entity))
+            .sorted_by_key(|(_, entity)| {
+                (
+                    distance(from, entity.position),
+                    position_order(entity.position),
+                )
+            })
+            .collect();
+
+        // Shield counts as health when looking for the weakest/strongest
+        let health = |entity: &Entity| entity.health.value() + entity.shield;
         let target = match target {
-            Target::Nearest => self
-                .model
-                .entities
-                .iter()
-                .filter(|(_, entity)| source_fraction != entity.fraction)
-                .min_by_key(|(_, entity)| distance(entity.position, self.board_item.position))
-                .map(|(i, _)| i),
-            Target::Random => self
-                .model
-                .entities
-                .iter()
-                .filter(|(_, entity)| source_fraction != entity.fraction)
-                .choose(&mut rng)
-                .map(|(i, _)| i),
+            Target::AllInRow | Target::AllInColumn => {
+                return candidates.into_iter().map(|(id, _)| id).collect();
+            }
+            Target::Nearest
+            | Target::InLine(_)
+            | Target::AtPosition(_)
+            | Target::Observed
+            | Target::Unobserved => candidates.first(),
+            Target::Farthest => candidates.iter().min_by_key(|(_, entity)| {
+                (
+                    -distance(from, entity.position),
+                    position_order(entity.position),
+                )
+            }),
+            Target::Weakest => candidates.iter().min_by_key(|(_, entity)| health(*entity)),
+            Target::Strongest => candidates.iter().min_by_key(|(_, entity)| -health(*entity)),
+            Target::Random => candidates.choose(&mut *self.model.rng.borrow_mut()),
         };
+        target.map(|&(id, _)| id).into_iter().collect()
+    }
 
-        if let Some(target) = target {
-            self.effects().damage(target, damage);
-        } else {
-            log::debug!("Item tried attacking but no target was found");
-        }
+    /// All entities hostile to the item, ordered by position.
+    pub fn enemies(&self) -> Vec<Id> {
+        self.model
+            .entities
+            .iter()
+            .filter(|(_, entity)| self.is_hostile(entity))
+            .sorted_by_key(|(_, entity)| position_order(entity.position))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// The enemy that `Target::Nearest` would hit.
+    pub fn nearest_enemy(&self) -> Option<Id> {
+        self.find_targets(&Target::Nearest).first().copied()
+    }
+
+    pub fn player(&self) -> Option<Id> {
+        self.model
+            .entities
+            .iter()
+            .find(|(_, entity)| matches!(entity.kind, EntityKind::Player))
+            .map(|(id, _)| id)
     }
 
-    pub fn damage_all_nearby(&mut self, range: Option<Coord>, damage: ScriptFunction) {
+    pub fn distance_to(&self, position: vec2<Coord>) -> Coord {
+        distance(self.board_item.position, position)
+    }
+
+    /// Damage all enemies in range (or on the whole board) that fit the target, if it is specified.
+    pub fn damage_all_nearby(
+        &mut self,
+        range: Option<Coord>,
+        target: Option<Target>,
+        damage: ScriptFunction,
+    ) {
         let damage = Rc::new(damage);
-        let source_fraction = Fraction::Player; // TODO: non-player items?
-        for (target, _) in self.model.entities.iter().filter(|(_, entity)| {
-            source_fraction != entity.fraction
-                && range.map_or(true, |range| {
-                    distance(entity.position, self.board_item.position) <= range
-                })
-        }) {
+        let targets: Vec<Id> = self
+            .model
+            .entities
+            .iter()
+            .filter(|(_, entity)| {
+                self.is_hostile(entity)
+                    && range.map_or(true, |range| {
+                        distance(entity.position, self.board_item.position) <= range
+                    })
+                    && target
+                        .as_ref()
+                        .map_or(true, |target| self.fits_target(target, entity))
+            })
+            .sorted_by_key(|(_, entity)| position_order(entity.position))
+            .map(|(id, _)| id)
+            .collect();
+        for target in targets {
             self.effects().damage(target, damage.clone());
         }
     }
@@ -73,7 +169,7 @@ impl ScriptItem<'_> {
         for (_, board_item) in &self.model.items {
             let item = &self.model.player.items[board_item.item_id];
             let dist = distance(board_item.position, self.board_item.position);
-            if (1..=range).contains(&dist) && filter.check(&item.kind) {
+            if (1..=range).contains(&dist) && filter.check(item) {
                 self.effects().bonus(
                     board_item.position,
                     self.item.on_board.unwrap(),
@@ -88,7 +184,7 @@ impl ScriptItem<'_> {
         for (_, board_item) in &self.model.items {
             let item = &self.model.player.items[board_item.item_id];
             let dist = distance_manhattan(board_item.position, self.board_item.position);
-            if dist == 1 && filter.check(&item.kind) {
+            if dist == 1 && filter.check(item) {
                 self.effects().bonus(
                     board_item.position,
                     self.item.on_board.unwrap(),
@@ -109,7 +205,7 @@ impl ScriptItem<'_> {
         for (target, board_item) in &self.model.items {
             let item = &self.model.player.items[board_item.item_id];
             let dist = distance(board_item.position, self.board_item.position);
-            if (1..=range).contains(&dist) && filter.check(&item.kind) {
+            if (1..=range).contains(&dist) && filter.check(item) {
                 self.effects()
                     .bonus(self.board_item.position, target, bonus.clone(), permanent);
             }
@@ -119,7 +215,7 @@ impl ScriptItem<'_> {
     pub fn bonus_to_all(&mut self, filter: ItemFilter, bonus: ItemStats, permanent: bool) {
         for (target, board_item) in &self.model.items {
             let item = &self.model.player.items[board_item.item_id];
-            if filter.check(&item.kind) {
+            if filter.check(item) {
                 self.effects()
                     .bonus(self.board_item.position, target, bonus.clone(), permanent);
             }
@@ -137,14 +233,21 @@ impl ScriptItem<'_> {
     }
 
     pub fn get_new_item(&mut self, filter: Option<ItemFilter>) {
-        if let Some(item) = self
+        let mut rng = self.model.rng.borrow_mut();
+        let item = self
             .model
             .all_items
             .iter()
-            .filter(|item| filter.as_ref().map_or(true, |filter| filter.check(item)))
-            .choose(&mut thread_rng())
-        {
-            self.effects().new_item(item.clone());
+            .filter(|item| {
+                filter
+                    .as_ref()
+                    .map_or(true, |filter| filter.check_kind(item))
+            })
+            .choose(&mut *rng)
+            .cloned();
+        drop(rng);
+        if let Some(item) = item {
+            self.effects().new_item(item);
         }
     }
 
@@ -154,33 +257,59 @@ impl ScriptItem<'_> {
             .iter()
             .filter(|(i, board_item)| {
                 let item = &self.model.player.items[board_item.item_id];
-                *i != self.item.on_board.unwrap() && filter.check(&item.kind)
+                *i != self.item.on_board.unwrap() && filter.check(item)
             })
             .map(|(id, _)| id)
-            .choose_multiple(&mut thread_rng(), count)
+            .choose_multiple(&mut *self.model.rng.borrow_mut(), count)
     }
 
     pub fn find_nearby(&self, range: Coord, filter: ItemFilter) -> Option<Id> {
         let items = self.model.items.iter().filter(|(_, board_item)| {
             let item = &self.model.player.items[board_item.item_id];
             let dist = distance(board_item.position, self.board_item.position);
-            (1..=range).contains(&dist) && filter.check(&item.kind)
+            (1..=range).contains(&dist) && filter.check(item)
         });
-        items.choose(&mut thread_rng()).map(|(id, _)| id)
+        items
+            .choose(&mut *self.model.rng.borrow_mut())
+            .map(|(id, _)| id)
+    }
+
+    /// The number of items the player has (on the board or not) that match the filter.
+    pub fn count_items(&self, filter: ItemFilter) -> usize {
+        self.model
+            .player
+            .items
+            .iter()
+            .filter(|(_, item)| filter.check(item))
+            .count()
     }
 
     pub fn find_random(&self, filter: ItemFilter) -> Option<Id> {
         let items = self.model.items.iter().filter(|(i, board_item)| {
             let item = &self.model.player.items[board_item.item_id];
-            *i != self.item.on_board.unwrap() && filter.check(&item.kind)
+            *i != self.item.on_board.unwrap() && filter.check(item)
         });
-        items.choose(&mut thread_rng()).map(|(id, _)| id)
+        items
+            .choose(&mut *self.model.rng.borrow_mut())
+            .map(|(id, _)| id)
+    }
+
+    pub fn rng_float(&self) -> f32 {
+        self.model.rng.borrow_mut().gen()
     }
 
     pub fn duplicate(&mut self) {
         self.effects().duplicate(self.board_item.item_id);
     }
 
+    /// Keep the item in place for the next shifts.
+    pub fn anchor(&mut self, shifts: usize) {
+        let Some(id) = self.item.on_board else {
+            return;
+        };
+        self.effects().anchor(id, shifts);
+    }
+
     pub fn gain_moves(&mut self, moves: usize) {
         self.effects().gain_moves(moves);
     }
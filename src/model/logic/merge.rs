@@ -0,0 +1,88 @@
+use super::*;
+
+impl Model {
+    /// Merge every group of copies of the same item and tier, until none are left.
+    pub(super) fn merge_all(&mut self) {
+        while let Some(item_id) = self.find_mergeable() {
+            self.merge_item(item_id);
+        }
+    }
+
+    /// An item that has enough copies to be merged, preferring the ones on the board.
+    fn find_mergeable(&self) -> Option<Id> {
+        let state = self.state.borrow();
+        state
+            .player
+            .items
+            .iter()
+            .sorted_by_key(|&(id, item)| (item.on_board.is_none(), id))
+            .map(|(id, _)| id)
+            .find(|&id| self.merge_copies(&state, id).is_some())
+    }
+
+    /// The copies that would be consumed by merging them into the item.
+    /// Returns `None` if there are not enough of them or the item is at the max tier.
+    pub fn merge_copies(&self, state: &ModelState, item_id: Id) -> Option<Vec<Id>> {
+        let merge = &self.config.merge;
+        let item = state.player.items.get(item_id)?;
+        if item.tier >= merge.max_tier {
+            return None;
+        }
+        // Consume the copies in the inventory first
+        let copies: Vec<Id> = state
+            .player
+            .items
+            .iter()
+            .filter(|&(id, other)| {
+                id != item_id
+                    && other.tier == item.tier
+                    && other.kind.config.name == item.kind.config.name
+            })
+            .sorted_by_key(|&(id, other)| (other.on_board.is_some(), id))
+            .map(|(id, _)| id)
+            .take(merge.copies.saturating_sub(1))
+            .collect();
+        (copies.len() + 1 >= merge.copies).then_some(copies)
+    }
+
+    /// Consume the copies of the item to raise its tier.
+    /// The copies are removed without triggering them.
+    /// Returns `false` if there are not enough copies.
+    pub(super) fn merge_item(&mut self, item_id: Id) -> bool {
+        let mut state = self.state.borrow_mut();
+        let Some(copies) = self.merge_copies(&state, item_id) else {
+            log::error!("Item {:?} cannot be merged", item_id);
+            return false;
+        };
+        for id in copies {
+            if let Some(copy) = state.player.items.remove(id) {
+                if let Some(board_id) = copy.on_board {
+                    state.items.remove(board_id);
+                }
+            }
+        }
+
+        let item = &mut state.player.items[item_id];
+        item.tier += 1;
+        let bonus = tier_bonus(&self.config.merge, &item.kind);
+        item.base_stats = item.base_stats.combine(&bonus);
+        log::debug!("Merged {:?} into tier {}", item.kind.config.name, item.tier);
+        true
+    }
+}
+
+/// The base stats of the item of the given tier, with the bonuses of all the merges.
+pub(super) fn tiered_base_stats(config: &MergeConfig, kind: &ItemKind, tier: usize) -> ItemStats {
+    let bonus = tier_bonus(config, kind);
+    (1..tier).fold(kind.config.base_stats.clone(), |stats, _| {
+        stats.combine(&bonus)
+    })
+}
+
+/// The bonus to the base stats gained with every tier.
+fn tier_bonus(config: &MergeConfig, kind: &ItemKind) -> ItemStats {
+    match &kind.config.tier_bonus {
+        Some(bonus) => bonus.clone(),
+        None => config.tier_bonus.restricted_to(&kind.config.base_stats),
+    }
+}
@@ -1,15 +1,28 @@
 mod action;
 mod animation;
 pub mod effect;
+mod enemy;
 mod engine;
 mod gen;
 mod item;
+mod merge;
+mod reaction;
+mod reload;
 mod resolve;
+#[cfg(test)]
+mod tests;
 
 use super::*;
 
 impl Model {
     pub fn update(&mut self, delta_time: Time) {
+        self.check_inventory_capacity();
+        if let Phase::Discard { .. } = self.phase {
+            // Everything waits for the player to make space
+            self.ticks += 1;
+            return;
+        }
+
         if let Phase::Map { .. } = self.phase {
             if self.state.borrow().grid.is_max() {
                 let mut phase = Phase::Vision;
@@ -38,11 +51,14 @@ impl Model {
                 }
             }
         }
+
+        self.ticks += 1;
     }
 
     /// Returns `true` when all effects are processed and executed.
     fn wait_for_effects(&self) -> bool {
         self.resolution_queue.is_empty()
+            && self.enemy_queue.is_empty()
             && self.effect_queue_stack.is_empty()
             && self.wait_for_animations()
     }
@@ -52,6 +68,21 @@ impl Model {
         self.animations.is_empty() && self.ending_animations.is_empty()
     }
 
+    /// Returns `true` when the model is idle and expects an input from the player.
+    pub fn is_waiting_for_input(&self) -> bool {
+        match self.phase {
+            Phase::Player => self.wait_for_effects() && self.state.borrow().player.moves_left > 0,
+            Phase::Placement { .. } => self.wait_for_effects(),
+            Phase::Vision
+            | Phase::Map { .. }
+            | Phase::Portal { .. }
+            | Phase::Discard { .. }
+            | Phase::Select { .. }
+            | Phase::GameOver => true,
+            _ => false,
+        }
+    }
+
     pub fn get_light_level(&self, position: vec2<Coord>) -> f32 {
         let state = self.state.borrow();
         match self.phase {
@@ -81,6 +112,7 @@ impl Model {
         };
 
         self.resolve_all(Trigger::Night);
+        self.resolve_all_enemies(EnemyTrigger::Night);
     }
 
     pub fn dawn_phase(&mut self) {
@@ -125,6 +157,9 @@ impl Model {
         log::debug!("Day phase");
         self.phase = Phase::Player;
         self.state.borrow_mut().player.moves_left = 3;
+
+        let saved = self.save_run();
+        self.events.push(ModelEvent::Checkpoint(Box::new(saved)));
     }
 
     fn player_phase(&mut self) {
@@ -143,25 +178,13 @@ impl Model {
 
     fn select_phase(&mut self, items: usize) {
         log::debug!("Select phase");
+        if self.config.merge.auto {
+            self.merge_all();
+        }
         self.update_vision();
 
         if items > 0 {
-            let state = self.state.borrow();
-
-            let map_full = state.grid.is_max();
-
-            let options: Vec<_> = state
-                .all_items
-                .iter()
-                .filter(|item| item.config.appears_in_shop.check(map_full))
-                .collect();
-            let mut rng = thread_rng();
-            let options = (0..3)
-                .map(|_| {
-                    let item = options.choose(&mut rng).unwrap();
-                    (*item).clone()
-                })
-                .collect();
+            let options = self.shop_options(SHOP_OPTIONS);
             self.phase = Phase::Select {
                 options,
                 extra_items: items - 1,
@@ -171,6 +194,43 @@ impl Model {
         }
     }
 
+    /// Choose different kinds of items to offer, according to their shop weights.
+    /// There might be fewer options if not enough items can be offered.
+    fn shop_options(&self, count: usize) -> Vec<ItemKind> {
+        let state = self.state.borrow();
+        let map_full = state.grid.is_max();
+        let owns = |name: &Rc<str>| {
+            state
+                .player
+                .items
+                .iter()
+                .any(|(_, item)| item.kind.config.name == *name)
+        };
+
+        let mut pool: Vec<&ItemKind> = state
+            .all_items
+            .iter()
+            .filter(|item| {
+                let shop = &item.config.shop;
+                item.config.appears_in_shop.check(map_full)
+                    && shop.offered_on(self.level)
+                    && shop.requires.iter().all(|name| owns(name))
+            })
+            .collect();
+
+        let mut rng = state.rng.borrow_mut();
+        let mut options = Vec::with_capacity(count);
+        while options.len() < count {
+            // Fails when the pool is empty or all weights are zero
+            let Ok(&item) = pool.choose_weighted(&mut *rng, |item| item.config.shop.weight) else {
+                break;
+            };
+            pool.retain(|other| other.config.name != item.config.name);
+            options.push(item.clone());
+        }
+        options
+    }
+
     fn next_turn(&mut self) {
         log::debug!("Next turn");
         self.turn += 1;
@@ -208,20 +268,60 @@ impl Model {
             win,
             timer: Lifetime::new_max(r32(0.0)),
         };
+        self.resolve_all(Trigger::LevelEnd);
+    }
+
+    /// Put the new item into the inventory and let the items on the board know.
+    fn acquire_item(&mut self, item: InventoryItem) -> Id {
+        let item_id = self.state.borrow_mut().player.items.insert(item);
+        self.resolve_all(Trigger::ItemAcquired);
+        item_id
+    }
+
+    /// Ask the player to discard items if there are too many of them.
+    /// Waits for the effects to resolve, since they might still change the inventory.
+    fn check_inventory_capacity(&mut self) {
+        if let Phase::Discard { .. } | Phase::GameOver = self.phase {
+            return;
+        }
+        if !self.wait_for_effects() {
+            return;
+        }
+        let items = self.state.borrow().player.items.len();
+        if items <= self.config.inventory_capacity {
+            return;
+        }
+
+        log::debug!(
+            "Inventory is over capacity: {}/{}",
+            items,
+            self.config.inventory_capacity
+        );
+        let mut next_phase = Phase::Vision;
+        std::mem::swap(&mut self.phase, &mut next_phase);
+        self.phase = Phase::Discard {
+            next_phase: Box::new(next_phase),
+        };
     }
 
     fn game_over(&mut self) {
         log::info!("Game over");
         self.phase = Phase::GameOver;
+        self.events.push(ModelEvent::GameOver);
     }
 
     fn retry(&mut self) {
         log::debug!("Retry");
-        *self = Self::new(
-            self.assets.clone(),
+        let seed = self.state.borrow().rng.borrow_mut().gen();
+        match Self::new(
             self.config.clone(),
             self.item_assets.clone(),
-        );
+            self.enemy_assets.clone(),
+            seed,
+        ) {
+            Ok(model) => *self = model,
+            Err(err) => log::error!("Failed to start a new run: {:?}", err),
+        }
     }
 
     fn calculate_empty_space(&self) -> HashSet<vec2<Coord>> {
@@ -262,6 +362,17 @@ impl Model {
         state.visible_tiles = visible;
     }
 
+    /// Board items on the visible tiles.
+    fn observed_items(&self) -> Vec<Id> {
+        let state = self.state.borrow();
+        state
+            .items
+            .iter()
+            .filter(|(_, item)| state.visible_tiles.contains(&item.position))
+            .map(|(id, _)| id)
+            .collect()
+    }
+
     fn check_deaths(&mut self) {
         let state = self.state.borrow();
 
@@ -321,3 +432,21 @@ fn distance_manhattan(a: vec2<Coord>, b: vec2<Coord>) -> Coord {
     let delta = b - a;
     delta.x.abs() + delta.y.abs()
 }
+
+/// The number of items offered in the shop.
+const SHOP_OPTIONS: usize = 3;
+
+/// The key to sort positions by: left to right, top to bottom.
+fn position_order(position: vec2<Coord>) -> (Coord, Coord) {
+    (position.x, -position.y)
+}
+
+/// Choose a random position from the set.
+/// The set is sorted beforehand, so that the result only depends on the state of the rng.
+fn choose_position(positions: &HashSet<vec2<Coord>>, rng: &mut impl Rng) -> Option<vec2<Coord>> {
+    positions
+        .iter()
+        .copied()
+        .sorted_by_key(|pos| (pos.x, pos.y))
+        .choose(rng)
+}
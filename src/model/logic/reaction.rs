@@ -0,0 +1,204 @@
+use super::*;
+
+// NOTE: expose functions in src/model/engine.rs
+impl PendingEffect {
+    /// Whether the items can react to the effect.
+    pub fn is_reactable(effect: &Effect) -> bool {
+        matches!(
+            effect,
+            Effect::Damage { .. }
+                | Effect::Bonus { .. }
+                | Effect::Destroy { .. }
+                | Effect::Duplicate { .. }
+                | Effect::TransformItem { .. }
+                | Effect::UseItem { .. }
+        )
+    }
+
+    /// The name of the effect as seen by the scripts.
+    pub fn kind_name(&self) -> &'static str {
+        match self.inner() {
+            Some(Effect::Damage { .. }) => "Damage",
+            Some(Effect::Bonus { .. }) => "Bonus",
+            Some(Effect::Destroy { .. }) => "Destroy",
+            Some(Effect::Duplicate { .. }) => "Duplicate",
+            Some(Effect::TransformItem { .. }) => "Transform",
+            Some(Effect::UseItem { .. }) => "Use",
+            _ => "Other",
+        }
+    }
+
+    /// The board item the effect is applied to.
+    pub fn target_item(&self, state: &ModelState) -> Option<Id> {
+        match *self.inner()? {
+            Effect::Bonus { target, .. } => Some(target),
+            Effect::UseItem { item } => Some(item),
+            Effect::Destroy { item_id }
+            | Effect::Duplicate { item_id }
+            | Effect::TransformItem { item_id, .. } => state.player.items.get(item_id)?.on_board,
+            _ => None,
+        }
+    }
+
+    /// The entity the effect is applied to.
+    pub fn target_entity(&self) -> Option<Id> {
+        match *self.inner()? {
+            Effect::Damage { target, .. } => Some(target),
+            _ => None,
+        }
+    }
+
+    /// Apply the effect to another board item instead.
+    /// Returns `false` if the effect does not target items.
+    pub fn redirect(&mut self, state: &ModelState, board_id: Id) -> bool {
+        let Some(board_item) = state.items.get(board_id) else {
+            return false;
+        };
+        match self.inner_mut() {
+            Some(Effect::Bonus { target, .. } | Effect::UseItem { item: target }) => {
+                *target = board_id
+            }
+            Some(
+                Effect::Destroy { item_id }
+                | Effect::Duplicate { item_id }
+                | Effect::TransformItem { item_id, .. },
+            ) => *item_id = board_item.item_id,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Returns `false` if the effect is not damage.
+    pub fn add_damage(&mut self, amount: Hp) -> bool {
+        match self.inner_mut() {
+            Some(Effect::Damage { extra, .. }) => {
+                *extra += amount;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn bonus(&self) -> Option<&ItemStats> {
+        match self.inner() {
+            Some(Effect::Bonus { bonus, .. }) => Some(bonus),
+            _ => None,
+        }
+    }
+
+    /// Returns `false` if the effect is not a bonus.
+    pub fn set_bonus(&mut self, stats: ItemStats) -> bool {
+        match self.inner_mut() {
+            Some(Effect::Bonus { bonus, .. }) => {
+                *bonus = stats;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The effect, unless it has already been taken out.
+    fn inner(&self) -> Option<&Effect> {
+        self.effect.as_ref().map(|effect| &effect.effect)
+    }
+
+    fn inner_mut(&mut self) -> Option<&mut Effect> {
+        self.effect.as_mut().map(|effect| &mut effect.effect)
+    }
+}
+
+impl Model {
+    /// Let the items on the board react to the effect before it is resolved.
+    /// Reactors are called in the same order as the triggers (left to right, top to bottom),
+    /// and each one sees the changes made by the previous ones.
+    /// Effects produced by the reactions are queued after the effect.
+    /// Returns `None` if the effect has been cancelled.
+    pub(super) fn react_to_effect(&mut self, effect: QueuedEffect) -> Option<QueuedEffect> {
+        if !PendingEffect::is_reactable(&effect.effect) {
+            return Some(effect);
+        }
+
+        let state = self.state.borrow();
+        let reactors: Vec<Id> = state
+            .items
+            .iter()
+            .filter(|(_, board_item)| {
+                let item = &state.player.items[board_item.item_id];
+                self.engine.handles(&item.kind, REACT_METHOD)
+            })
+            .sorted_by_key(|(_, board_item)| position_order(board_item.position))
+            .map(|(id, _)| id)
+            .collect();
+        drop(state);
+        if reactors.is_empty() {
+            return Some(effect);
+        }
+
+        let pending = Rc::new(RefCell::new(PendingEffect {
+            effect: Some(effect),
+            cancelled: false,
+        }));
+        for id in reactors {
+            if pending.borrow().cancelled {
+                break;
+            }
+            self.react_item(id, &pending);
+        }
+
+        // Scripts might still hold on to the effect, so take it out instead of unwrapping
+        let mut pending = pending.borrow_mut();
+        let effect = pending.effect.take()?;
+        if pending.cancelled {
+            log::debug!("Effect {:?} has been cancelled", effect);
+            return None;
+        }
+        Some(effect)
+    }
+
+    /// Call the item's `react` handler and queue the effects it produced.
+    fn react_item(&mut self, item_id: Id, pending: &Rc<RefCell<PendingEffect>>) {
+        let state = self.state.borrow();
+        let Some(board_item) = state.items.get(item_id) else {
+            return;
+        };
+        let item = &state.player.items[board_item.item_id];
+
+        // NOTE: requires immutable access to [ModelState]
+        let result = self.engine.item_react(item, board_item, pending);
+        let effects = std::mem::take(&mut *self.side_effects.borrow_mut());
+        let memory = match result {
+            Ok(memory) => memory,
+            Err(err) => {
+                report_error(&mut self.events, ModelError::script(&item.kind, err));
+                return;
+            }
+        };
+
+        // Update item memory
+        let inventory_id = board_item.item_id;
+        drop(state);
+        self.state.borrow_mut().player.items[inventory_id].memory = memory;
+
+        if effects.is_empty() || self.effect_chain_too_deep(item_id) {
+            return;
+        }
+        log::debug!("Item {:?} reacted: {:?}", item_id, effects);
+        let Some(trigger) = pending
+            .borrow()
+            .effect
+            .as_ref()
+            .map(|effect| effect.trigger)
+        else {
+            return;
+        };
+        let effects = effects
+            .into_iter()
+            .map(|effect| QueuedEffect {
+                trigger,
+                proc_item: Some(item_id),
+                effect,
+            })
+            .collect();
+        self.effect_queue_stack.push(effects);
+    }
+}
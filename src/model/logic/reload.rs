@@ -0,0 +1,75 @@
+use super::*;
+
+impl Model {
+    /// Recompile the item and replace its kind in the pool and in the inventory.
+    /// Stats modifications and the memory of the existing items are kept.
+    /// If the script does not compile, nothing is changed.
+    ///
+    /// *NOTE*: an item with a changed name is added as a new kind, the old one stays.
+    pub fn reload_item(&mut self, asset: ItemAsset) -> Result<(), ItemReport> {
+        let kind = self
+            .engine
+            .recompile_item(&asset, &self.item_assets.library)?;
+        log::info!("Reloading item {:?}", kind.config.name);
+
+        Rc::make_mut(&mut self.item_assets)
+            .assets
+            .insert(Rc::clone(&kind.config.name), asset);
+        self.replace_kind(kind);
+
+        Ok(())
+    }
+
+    /// Replace the script library and recompile the items that use it.
+    /// If any of them does not compile, nothing is changed.
+    /// Returns the names of the recompiled items.
+    pub fn reload_library(&mut self, library: ScriptLibrary) -> Result<Vec<Rc<str>>, ItemReport> {
+        let kinds = self
+            .item_assets
+            .assets
+            .values()
+            .filter(|asset| asset.uses_library())
+            .sorted_by(|a, b| a.config.name.cmp(&b.config.name))
+            .map(|asset| self.engine.recompile_item(asset, &library))
+            .collect::<Result<Vec<_>, _>>()?;
+        log::info!("Reloading the script library");
+
+        Rc::make_mut(&mut self.item_assets).library = library;
+        let names = kinds
+            .iter()
+            .map(|kind| Rc::clone(&kind.config.name))
+            .collect();
+        for kind in kinds {
+            self.replace_kind(kind);
+        }
+
+        Ok(names)
+    }
+
+    /// Put the recompiled kind into the pool and into the inventory.
+    fn replace_kind(&mut self, kind: ItemKind) {
+        let name = Rc::clone(&kind.config.name);
+        let mut state = self.state.borrow_mut();
+        match state
+            .all_items
+            .iter_mut()
+            .find(|other| other.config.name == name)
+        {
+            Some(old) => *old = kind.clone(),
+            None => {
+                state.all_items.push(kind.clone());
+                // Keep the order independent of the reloads for the rng to be deterministic
+                state
+                    .all_items
+                    .sort_by(|a, b| a.config.name.cmp(&b.config.name));
+            }
+        }
+        for (_, item) in &mut state.player.items {
+            if item.kind.config.name == name {
+                item.kind = kind.clone();
+                item.base_stats =
+                    super::merge::tiered_base_stats(&self.config.merge, &kind, item.tier);
+            }
+        }
+    }
+}
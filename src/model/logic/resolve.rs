@@ -30,18 +30,17 @@ impl Model {
             }
             Phase::LevelFinished { timer, .. } => {
                 timer.change(-delta_time);
-                if timer.is_min() {
+                // Let the items finish reacting to the end of the level
+                if timer.is_min() && wait_for_effects {
                     self.shift_everything();
                     self.next_level(false);
                 }
             }
             Phase::PostVision { timer } => {
                 timer.change(-delta_time);
-                if timer.is_min() {
-                    let mut state = self.state.borrow_mut();
-                    state.player.refreshes = 2;
-                    let extra = state.player.extra_items;
-                    drop(state);
+                // Let the observed items react before the shop
+                if timer.is_min() && wait_for_effects {
+                    let extra = self.state.borrow().player.extra_items;
                     self.select_phase(extra);
                 }
             }
@@ -120,7 +119,12 @@ impl Model {
 
         let state = self.state.borrow();
         let from_pos = target_pos;
-        let target_pos = state.entities[entity_id].position;
+        let Some(target_pos) = state.entities.get(entity_id).map(|entity| entity.position) else {
+            drop(state);
+            report_error(&mut self.events, ModelError::EntityNotFound(entity_id));
+            self.player_phase();
+            return;
+        };
         if let Some((entity_id, _)) = state.entities.iter().find(|(_, e)| e.position == from_pos) {
             self.animations.insert(Animation::new(
                 self.config.animation_time,
@@ -150,40 +154,100 @@ impl Model {
             start_delay: Lifetime::new_max(r32(0.2)),
         };
 
-        // What is this trick KEKW
-        let mut state = self.state.borrow_mut();
-        let state = &mut *state;
-
         // Reset vision
-        state.visible_tiles.clear();
+        self.state.borrow_mut().visible_tiles.clear();
+
+        self.resolve_all_enemies(EnemyTrigger::Day);
     }
 
     pub(super) fn resolve_trigger(&mut self, trigger: Trigger, id: Id) {
+        if self.effect_chain_too_deep(id) {
+            return;
+        }
+
         let effects = self
             .resolve_item(id, trigger)
             .into_iter()
             .map(move |effect| QueuedEffect {
-                trigger,
-                proc_item: id,
+                trigger: Some(trigger),
+                proc_item: Some(id),
                 effect,
             })
             .collect();
         self.effect_queue_stack.push(effects);
     }
 
+    /// Check whether another queue of effects from the item would make the chain too deep,
+    /// and report it if it would.
+    pub(super) fn effect_chain_too_deep(&mut self, id: Id) -> bool {
+        let depth = self.config.max_effect_depth;
+        if self.effect_chain_depth() < depth {
+            return false;
+        }
+
+        // Most likely the items keep triggering each other
+        let state = self.state.borrow();
+        if let Some(board_item) = state.items.get(id) {
+            let item = Rc::clone(&state.player.items[board_item.item_id].kind.config.name);
+            report_error(&mut self.events, ModelError::EffectChain { item, depth });
+        }
+        true
+    }
+
+    /// The depth of the effect chain that is currently resolving,
+    /// including the triggers that were queued along the way (e.g. by acquiring an item).
+    fn effect_chain_depth(&self) -> usize {
+        self.chain_depth + self.effect_queue_stack.len()
+    }
+
     pub(super) fn resolve_all(&mut self, trigger: Trigger) {
-        let mut ids: Vec<_> = self.state.borrow().items.iter().map(|(i, _)| i).collect();
+        let ids: Vec<_> = self.state.borrow().items.iter().map(|(i, _)| i).collect();
+        self.resolve_some(trigger, ids);
+    }
 
-        // Sort by item position
-        ids.sort_by_key(|&id| {
-            let pos = self.state.borrow().items[id].position;
-            // Left -> Right
-            // Top -> Bottom
-            (pos.x, -pos.y)
-        });
+    /// Queue the trigger for the board items, after the already queued ones.
+    /// Items that are no longer on the board are skipped.
+    pub(super) fn resolve_some(&mut self, trigger: Trigger, ids: Vec<Id>) {
+        let state = self.state.borrow();
+        let ids: Vec<Id> = ids
+            .into_iter()
+            .filter_map(|id| state.items.get(id).map(|item| (id, item.position)))
+            .sorted_by_key(|&(_, pos)| position_order(pos))
+            .map(|(id, _)| id)
+            .collect();
+        drop(state);
 
+        let depth = self.effect_chain_depth();
         self.resolution_queue
-            .extend(ids.into_iter().map(|id| (id, trigger)));
+            .extend(ids.into_iter().map(|id| (id, trigger, depth)));
+    }
+
+    /// Resolve the `Destroyed` trigger of the item, and remove it after the effects are done.
+    pub(super) fn destroy_item(&mut self, item_id: Id) {
+        let board_item = self
+            .state
+            .borrow()
+            .player
+            .items
+            .get(item_id)
+            .and_then(|item| item.on_board);
+        let Some(board_item) = board_item else {
+            // Not on the board, nothing to trigger
+            self.state.borrow_mut().player.items.remove(item_id);
+            return;
+        };
+
+        let effects = self
+            .resolve_item(board_item, Trigger::Destroyed)
+            .into_iter()
+            .chain([Effect::RemoveItem { item_id }])
+            .map(|effect| QueuedEffect {
+                trigger: Some(Trigger::Destroyed),
+                proc_item: Some(board_item),
+                effect,
+            })
+            .collect();
+        self.effect_queue_stack.push(effects);
     }
 
     /// Resolve the item's response to the trigger.
@@ -196,29 +260,33 @@ impl Model {
             return vec![];
         };
 
+        let item = &state.player.items[board_item.item_id];
+
         if let Trigger::Active = trigger {
             if board_item.used {
                 log::debug!("Item {:?} has already been activated", item_id);
                 return vec![];
             }
+            if matches!(item.current_stats().charges, Some(charges) if charges <= 0) {
+                log::debug!("Item {:?} has no charges left", item_id);
+                return vec![];
+            }
         }
 
-        let item = &state.player.items[board_item.item_id];
-
         // Execute
         // NOTE: requires immutable access to [ModelState]
-        let item_state = match self
+        let memory = match self
             .engine
             .item_trigger(item, board_item, trigger.method_name())
         {
-            Ok(state) => state,
+            Ok(memory) => memory,
             Err(err) => {
-                log::error!("Trigger handler failed: {:?}", err);
+                report_error(&mut self.events, ModelError::script(&item.kind, err));
                 return vec![];
             }
         };
 
-        // Update item state
+        // Update item memory
         drop(state);
         // What is this trick KEKW
         let mut state = self.state.borrow_mut();
@@ -231,8 +299,14 @@ impl Model {
             && !effects.iter().any(|e| matches!(e, Effect::Destroy { .. }))
         {
             effects.push(Effect::SetUsed { item_id });
+
+            // Spend a charge
+            let item = &mut state.player.items[board_item.item_id];
+            if item.current_stats().charges.is_some() {
+                item.perm_stats.charges = Some(item.perm_stats.charges.unwrap_or(0) - 1);
+            }
         }
-        state.player.items[board_item.item_id].state = item_state;
+        state.player.items[board_item.item_id].memory = memory;
 
         log::debug!("Item {:?} resolved: {:?}", item_id, effects);
         effects
@@ -0,0 +1,2424 @@
+use super::*;
+
+use crate::headless::{RandomBot, Simulation};
+
+use std::path::PathBuf;
+
+/// The maximum number of updates to wait for the effects to resolve.
+const MAX_STEPS: usize = 10_000;
+
+fn assets_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("assets")
+}
+
+/// A hand-made board to test item scripts on.
+struct Fixture {
+    seed: u64,
+    grid_size: Coord,
+    player: vec2<Coord>,
+    /// Items and their positions on the board, `None` for the ones only in the inventory.
+    items: Vec<(&'static str, Option<vec2<Coord>>)>,
+    /// Items defined by the test itself: name, base damage, and the script.
+    custom_items: Vec<(&'static str, Hp, String)>,
+    enemies: Vec<(&'static str, vec2<Coord>, Hp)>,
+    visible: Vec<vec2<Coord>>,
+}
+
+/// A model built from a [Fixture], with helpers to trigger items and inspect the results.
+struct TestModel {
+    model: Model,
+}
+
+impl Fixture {
+    /// A 3x3 grid with the player in the bottom left corner.
+    fn new() -> Self {
+        Self {
+            seed: 0,
+            grid_size: 3,
+            player: vec2(-1, -1),
+            items: Vec::new(),
+            custom_items: Vec::new(),
+            enemies: Vec::new(),
+            visible: Vec::new(),
+        }
+    }
+
+    fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    fn grid(mut self, size: Coord) -> Self {
+        self.grid_size = size;
+        self
+    }
+
+    fn player(mut self, position: vec2<Coord>) -> Self {
+        self.player = position;
+        self
+    }
+
+    fn item(mut self, name: &'static str, position: vec2<Coord>) -> Self {
+        self.items.push((name, Some(position)));
+        self
+    }
+
+    /// Put the item into the inventory without placing it on the board.
+    fn stashed_item(mut self, name: &'static str) -> Self {
+        self.items.push((name, None));
+        self
+    }
+
+    /// Define a new item kind with the script, to be placed with [Fixture::item].
+    fn custom_item(mut self, name: &'static str, damage: Hp, script: impl Into<String>) -> Self {
+        self.custom_items.push((name, damage, script.into()));
+        self
+    }
+
+    /// Place a dummy enemy, that does nothing.
+    fn enemy(self, position: vec2<Coord>, health: Hp) -> Self {
+        self.enemy_kind("Dummy", position, health)
+    }
+
+    fn enemy_kind(mut self, name: &'static str, position: vec2<Coord>, health: Hp) -> Self {
+        self.enemies.push((name, position, health));
+        self
+    }
+
+    /// Make the tile observed by the player.
+    fn visible(mut self, position: vec2<Coord>) -> Self {
+        self.visible.push(position);
+        self
+    }
+
+    fn build(self) -> TestModel {
+        let mut config =
+            Config::load_headless(assets_path().join("config.ron")).expect("failed to load config");
+        config.starting_items.clear();
+        let mut item_assets =
+            ItemAssets::load_headless(assets_path().join("items")).expect("failed to load items");
+        for (name, damage, script) in self.custom_items {
+            let config = ItemConfig {
+                name: name.into(),
+                categories: Rc::new([]),
+                tags: vec![],
+                appears_in_shop: ShopAppearance::Never,
+                base_stats: ItemStats {
+                    damage: Some(damage),
+                    ..Default::default()
+                },
+                memory: MemoryConfig::default(),
+                shop: ShopConfig::default(),
+                tier_bonus: None,
+                anchored: false,
+            };
+            let asset = ItemAsset {
+                config,
+                description: None,
+                script: Some(script),
+                texture: None,
+            };
+            item_assets.assets.insert(name.into(), asset);
+        }
+        let item_assets = Rc::new(item_assets);
+        let enemy_assets = Rc::new(
+            EnemyAssets::load_headless(assets_path().join("enemies"))
+                .expect("failed to load enemies"),
+        );
+
+        let player = Entity {
+            position: self.player,
+            fraction: Fraction::Player,
+            health: Health::new_max(100),
+            shield: 0,
+            look_dir: vec2(0, 0),
+            kind: EntityKind::Player,
+        };
+        let enemies = self
+            .enemies
+            .into_iter()
+            .map(|(name, position, health)| Entity {
+                position,
+                fraction: Fraction::Enemy,
+                health: Health::new_max(health),
+                shield: 0,
+                look_dir: vec2(0, -1),
+                kind: EntityKind::Enemy(name.into()),
+            });
+
+        let state = ModelState {
+            all_items: vec![],
+            all_enemies: vec![],
+            grid: Grid::new(self.grid_size),
+            player: Player {
+                moves_left: 3,
+                turns_left: 5,
+                ..Player::new()
+            },
+            items: Arena::new(),
+            entities: std::iter::once(player).chain(enemies).collect(),
+            visible_tiles: self.visible.into_iter().collect(),
+            rng: RefCell::new(StdRng::seed_from_u64(self.seed)),
+        };
+        let state = Rc::new(RefCell::new(state));
+        let side_effects = Rc::new(RefCell::new(Vec::new()));
+
+        let engine = Engine::new(
+            Rc::clone(&state),
+            Rc::clone(&side_effects),
+            config.script_budget,
+        )
+        .expect("failed to initialize the engine");
+        let all_items = engine
+            .compile_items(&item_assets, true)
+            .expect("failed to compile items");
+        let all_enemies = engine
+            .compile_enemies(&enemy_assets, true)
+            .expect("failed to compile enemies");
+
+        {
+            let mut state = state.borrow_mut();
+            let state = &mut *state;
+            for (name, position) in self.items {
+                let kind = all_items
+                    .iter()
+                    .find(|kind| *kind.config.name == *name)
+                    .unwrap_or_else(|| panic!("unknown item {}", name));
+                let item = engine
+                    .init_item(kind.clone())
+                    .expect("failed to initialize item");
+                let item_id = state.player.items.insert(item);
+                let Some(position) = position else {
+                    continue;
+                };
+                let on_board = state.items.insert(BoardItem {
+                    position,
+                    item_id,
+                    used: false,
+                    anchored: 0,
+                });
+                state.player.items[item_id].on_board = Some(on_board);
+            }
+            state.all_items = all_items;
+            state.all_enemies = all_enemies;
+        }
+
+        let mut model = Model::new_compiled(
+            config,
+            item_assets,
+            enemy_assets,
+            self.seed,
+            engine,
+            state,
+            side_effects,
+        );
+        model.phase = Phase::Player;
+        TestModel { model }
+    }
+}
+
+impl TestModel {
+    fn board_item(&self, position: vec2<Coord>) -> Option<Id> {
+        self.model
+            .state
+            .borrow()
+            .items
+            .iter()
+            .find(|(_, item)| item.position == position)
+            .map(|(id, _)| id)
+    }
+
+    /// Fire the trigger on the item at the position and resolve all the effects.
+    fn trigger(&mut self, position: vec2<Coord>, trigger: Trigger) {
+        let id = self
+            .board_item(position)
+            .unwrap_or_else(|| panic!("no item at {}", position));
+        self.model.resolve_trigger(trigger, id);
+        self.resolve();
+    }
+
+    fn input(&mut self, input: PlayerInput) {
+        self.model.player_action(input);
+        self.resolve();
+    }
+
+    /// Update the model until all effects and animations are done,
+    /// or until the model asks the player for a map or portal input.
+    fn resolve(&mut self) {
+        for _ in 0..MAX_STEPS {
+            if let Phase::Map { .. } | Phase::Portal { .. } = self.model.phase {
+                return;
+            }
+            if self.model.wait_for_effects() {
+                return;
+            }
+            self.model.update(r32(FIXED_DELTA_TIME));
+        }
+        panic!("effects did not resolve in {} steps", MAX_STEPS);
+    }
+
+    fn item_at(&self, position: vec2<Coord>) -> Option<String> {
+        let state = self.model.state.borrow();
+        let id = self.board_item(position)?;
+        let item = &state.player.items[state.items[id].item_id];
+        Some(item.kind.config.name.to_string())
+    }
+
+    fn with_item<T>(&self, position: vec2<Coord>, f: impl FnOnce(&mut InventoryItem) -> T) -> T {
+        let id = self
+            .board_item(position)
+            .unwrap_or_else(|| panic!("no item at {}", position));
+        let mut state = self.model.state.borrow_mut();
+        let state = &mut *state;
+        f(&mut state.player.items[state.items[id].item_id])
+    }
+
+    fn damage_at(&self, position: vec2<Coord>) -> Option<Hp> {
+        self.with_item(position, |item| item.current_stats().damage)
+    }
+
+    fn perm_damage_at(&self, position: vec2<Coord>) -> Option<Hp> {
+        self.with_item(position, |item| item.perm_stats.damage)
+    }
+
+    fn inventory_count(&self, name: &str) -> usize {
+        self.model
+            .state
+            .borrow()
+            .player
+            .items
+            .iter()
+            .filter(|(_, item)| *item.kind.config.name == *name)
+            .count()
+    }
+
+    fn inventory_len(&self) -> usize {
+        self.model.state.borrow().player.items.len()
+    }
+
+    fn enemy_health(&self, position: vec2<Coord>) -> Option<Hp> {
+        self.model
+            .state
+            .borrow()
+            .entities
+            .iter()
+            .find(|(_, entity)| entity.fraction == Fraction::Enemy && entity.position == position)
+            .map(|(_, entity)| entity.health.value())
+    }
+
+    fn entity_at(&self, position: vec2<Coord>) -> Option<(Id, Entity)> {
+        self.model
+            .state
+            .borrow()
+            .entities
+            .iter()
+            .find(|(_, entity)| entity.position == position)
+            .map(|(id, entity)| (id, entity.clone()))
+    }
+
+    /// Fire the trigger on the enemy at the position and resolve all the effects.
+    fn enemy_trigger(&mut self, position: vec2<Coord>, trigger: EnemyTrigger) {
+        let (id, entity) = self
+            .entity_at(position)
+            .unwrap_or_else(|| panic!("no enemy at {}", position));
+        self.model.resolve_enemy(id, &entity, trigger);
+        self.resolve();
+    }
+
+    fn enemy_count(&self, name: &str) -> usize {
+        self.model
+            .state
+            .borrow()
+            .entities
+            .iter()
+            .filter(
+                |(_, entity)| matches!(&entity.kind, EntityKind::Enemy(kind) if **kind == *name),
+            )
+            .count()
+    }
+
+    fn player_position(&self) -> vec2<Coord> {
+        self.model
+            .state
+            .borrow()
+            .entities
+            .iter()
+            .find(|(_, entity)| matches!(entity.kind, EntityKind::Player))
+            .map(|(_, entity)| entity.position)
+            .expect("player not found")
+    }
+}
+
+/// Run the same scenario for a range of seeds, and check that
+/// both the lucky and the unlucky outcomes have happened.
+fn for_seeds(mut test: impl FnMut(u64) -> bool) {
+    let lucky = (0..100).filter(|&seed| test(seed)).count();
+    assert!(lucky > 0, "the random effect never happened");
+    assert!(lucky < 100, "the random effect always happened");
+}
+
+#[test]
+fn sword_damages_nearest_enemy() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .enemy(vec2(1, 1), 5)
+        .enemy(vec2(1, -1), 5)
+        .player(vec2(-1, 1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    let damaged = [vec2(1, 1), vec2(1, -1)]
+        .into_iter()
+        .filter(|&pos| test.enemy_health(pos) == Some(3))
+        .count();
+    assert_eq!(damaged, 1);
+}
+
+#[test]
+fn sword_gains_damage_from_nearby_swords() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .item("Sword", vec2(0, 1))
+        .enemy(vec2(1, 0), 10)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 0)), Some(6));
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(4));
+    assert_eq!(test.perm_damage_at(vec2(0, 0)), None);
+}
+
+#[test]
+fn sword_kills_enemy() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .enemy(vec2(1, 0), 2)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 0)), None);
+}
+
+#[test]
+fn forge_buffs_nearby_weapons() {
+    let mut test = Fixture::new()
+        .grid(5)
+        .item("Forge", vec2(0, 0))
+        .item("Sword", vec2(1, 1))
+        .item("Sword", vec2(2, 2))
+        .item("Ultra speed shoes", vec2(0, 1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    assert_eq!(test.damage_at(vec2(1, 1)), Some(4));
+    assert_eq!(test.perm_damage_at(vec2(1, 1)), None);
+    assert_eq!(test.damage_at(vec2(2, 2)), Some(2));
+    assert_eq!(test.damage_at(vec2(0, 1)), None);
+    // Only the damage is changed
+    test.with_item(vec2(1, 1), |item| {
+        assert_eq!(item.current_stats().range, None);
+        assert_eq!(item.current_stats().moves, None);
+    });
+}
+
+#[test]
+fn boots_give_moves() {
+    let mut test = Fixture::new().item("Ultra speed shoes", vec2(0, 0)).build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.model.state.borrow().player.moves_left, 6);
+    assert_eq!(test.item_at(vec2(0, 0)), None);
+    assert_eq!(test.inventory_len(), 0);
+}
+
+#[test]
+fn charges_limit_activations() {
+    let mut test = Fixture::new()
+        .custom_item("Wand", 1, weapon_script("Target::Nearest"))
+        .item("Wand", vec2(0, 0))
+        .enemy(vec2(1, 1), 5)
+        .build();
+    test.with_item(vec2(0, 0), |item| item.base_stats.charges = Some(2));
+
+    for _ in 0..3 {
+        test.trigger(vec2(0, 0), Trigger::Active);
+        for (_, item) in &mut test.model.state.borrow_mut().items {
+            item.used = false;
+        }
+    }
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(3));
+    test.with_item(vec2(0, 0), |item| {
+        assert_eq!(item.current_stats().charges, Some(0));
+    });
+}
+
+#[test]
+fn damage_only_stats() {
+    let script = r#"
+        pub fn active(item) {
+            item.bonus(Stats::damage(2), true);
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Whetstone", 1, script)
+        .item("Whetstone", vec2(0, 0))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.perm_damage_at(vec2(0, 0)), Some(2));
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(3));
+}
+
+#[test]
+fn map_opens_tiles() {
+    let mut test = Fixture::new().item("Grand map", vec2(0, 0)).build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert!(matches!(test.model.phase, Phase::Map { tiles_left: 2, .. }));
+
+    test.input(PlayerInput::Tile(vec2(2, 0)));
+    test.input(PlayerInput::Tile(vec2(-2, 0)));
+    assert!(matches!(test.model.phase, Phase::Player));
+    let state = test.model.state.borrow();
+    assert_eq!(state.grid.tiles.len(), 11);
+    assert!(state.grid.check_pos(vec2(2, 0)) && state.grid.check_pos(vec2(-2, 0)));
+    drop(state);
+    assert_eq!(test.item_at(vec2(0, 0)), None);
+}
+
+#[test]
+fn camera_duplicates_nearby_spooky() {
+    let mut test = Fixture::new()
+        .item("Camera", vec2(0, 0))
+        .item("Ghost", vec2(1, 1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.inventory_count("Ghost"), 2);
+    assert_eq!(test.inventory_count("Camera"), 0);
+}
+
+#[test]
+fn camera_does_nothing_without_spooky() {
+    let mut test = Fixture::new()
+        .item("Camera", vec2(0, 0))
+        .item("Sword", vec2(1, 1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.inventory_count("Camera"), 1);
+    assert_eq!(test.inventory_len(), 2);
+}
+
+#[test]
+fn ghost_uses_nearby_weapon() {
+    let mut test = Fixture::new()
+        .item("Ghost", vec2(0, 0))
+        .item("Sword", vec2(0, 1))
+        .enemy(vec2(1, 1), 5)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayAction);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(3));
+}
+
+#[test]
+fn ghost_disappears_when_observed() {
+    let mut test = Fixture::new()
+        .item("Ghost", vec2(0, 0))
+        .item("Ghost", vec2(1, 1))
+        .visible(vec2(0, 0))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Night);
+    test.trigger(vec2(1, 1), Trigger::Night);
+    assert_eq!(test.item_at(vec2(0, 0)), None);
+    assert_eq!(test.item_at(vec2(1, 1)).as_deref(), Some("Ghost"));
+}
+
+#[test]
+fn fire_scroll_burns_once() {
+    let mut test = Fixture::new()
+        .item("Fire scroll", vec2(0, 0))
+        .enemy(vec2(1, 1), 10)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(5));
+    assert_eq!(test.inventory_len(), 0);
+}
+
+#[test]
+fn soul_crystal_charges_up() {
+    let mut test = Fixture::new()
+        .item("Soul crystal", vec2(0, 0))
+        .enemy(vec2(1, 1), 5)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    assert_eq!(test.perm_damage_at(vec2(0, 0)), Some(2));
+
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(3));
+    assert_eq!(test.inventory_len(), 0);
+}
+
+#[test]
+fn radiation_core_damages_adjacent_enemies() {
+    let mut test = Fixture::new()
+        .grid(5)
+        .item("Radiation core", vec2(0, 0))
+        .enemy(vec2(1, 1), 5)
+        .enemy(vec2(-1, 0), 5)
+        .enemy(vec2(2, 2), 5)
+        .player(vec2(-2, -2))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayAction);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(4));
+    assert_eq!(test.enemy_health(vec2(-1, 0)), Some(4));
+    assert_eq!(test.enemy_health(vec2(2, 2)), Some(5));
+}
+
+#[test]
+fn greedy_pot_attacks() {
+    let mut test = Fixture::new()
+        .item("Greedy pot", vec2(0, 0))
+        .enemy(vec2(1, 1), 5)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayAction);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(4));
+}
+
+#[test]
+fn greedy_pot_eats_treasure() {
+    for_seeds(|seed| {
+        let mut test = Fixture::new()
+            .seed(seed)
+            .item("Greedy pot", vec2(0, 0))
+            .item("Chest", vec2(1, 0))
+            .build();
+        test.trigger(vec2(0, 0), Trigger::DayBonus);
+        match test.item_at(vec2(1, 0)) {
+            Some(_) => {
+                assert_eq!(test.perm_damage_at(vec2(0, 0)), None);
+                false
+            }
+            None => {
+                assert_eq!(test.perm_damage_at(vec2(0, 0)), Some(2));
+                assert_eq!(test.inventory_count("Chest"), 0);
+                true
+            }
+        }
+    });
+}
+
+#[test]
+fn spirit_coin_multiplies_near_chest() {
+    let mut test = Fixture::new()
+        .item("Spirit coin", vec2(0, 0))
+        .item("Chest", vec2(1, 1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    assert_eq!(test.inventory_count("Spirit coin"), 2);
+
+    let mut test = Fixture::new()
+        .item("Spirit coin", vec2(0, 0))
+        .item("Sword", vec2(1, 1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    assert_eq!(test.inventory_count("Spirit coin"), 1);
+}
+
+#[test]
+fn spirit_coin_sometimes_attacks() {
+    for_seeds(|seed| {
+        let mut test = Fixture::new()
+            .seed(seed)
+            .item("Spirit coin", vec2(0, 0))
+            .enemy(vec2(1, 1), 10)
+            .build();
+        test.trigger(vec2(0, 0), Trigger::DayAction);
+        match test.enemy_health(vec2(1, 1)) {
+            Some(10) => {
+                assert_eq!(test.inventory_count("Spirit coin"), 1);
+                false
+            }
+            health => {
+                assert_eq!(health, Some(5));
+                assert_eq!(test.inventory_count("Spirit coin"), 0);
+                true
+            }
+        }
+    });
+}
+
+#[test]
+fn three_chests_open() {
+    let mut test = Fixture::new()
+        .item("Chest", vec2(0, 0))
+        .item("Chest", vec2(1, 0))
+        .item("Chest", vec2(0, 1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    for pos in [vec2(0, 0), vec2(1, 0), vec2(0, 1)] {
+        assert_eq!(test.item_at(pos), None);
+    }
+    // The new item goes straight into the inventory
+    assert_eq!(test.inventory_len(), 1);
+    assert!(test.model.state.borrow().items.is_empty());
+}
+
+#[test]
+fn two_chests_stay_closed() {
+    let mut test = Fixture::new()
+        .item("Chest", vec2(0, 0))
+        .item("Chest", vec2(1, 0))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    assert_eq!(test.inventory_count("Chest"), 2);
+    assert_eq!(test.inventory_len(), 2);
+}
+
+#[test]
+fn magic_treasure_bag_turns_into_treasure() {
+    let mut test = Fixture::new()
+        .item("Magic treasure bag", vec2(0, 0))
+        .build();
+    test.with_item(vec2(0, 0), |item| item.turns_on_board = 3);
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    assert_eq!(
+        test.item_at(vec2(0, 0)).as_deref(),
+        Some("Magic treasure bag")
+    );
+
+    test.with_item(vec2(0, 0), |item| item.turns_on_board = 4);
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    let name = test.item_at(vec2(0, 0)).expect("the item has disappeared");
+    assert_ne!(name, "Magic treasure bag");
+    assert!(test.with_item(vec2(0, 0), |item| {
+        item.kind.config.categories.contains(&Category::Treasure)
+    }));
+    assert_eq!(test.with_item(vec2(0, 0), |item| item.turns_on_board), 4);
+}
+
+#[test]
+fn electric_rod_charges_from_connected_tech() {
+    let mut test = Fixture::new()
+        .item("Electric rod", vec2(0, 0))
+        .item("Ultra speed shoes", vec2(1, 0))
+        .item("Camera", vec2(0, 1))
+        .item("Melter", vec2(1, 1))
+        .enemy(vec2(-1, 1), 10)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(-1, 1)), Some(6));
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(4));
+}
+
+#[test]
+fn magic_wire_sometimes_duplicates() {
+    for_seeds(|seed| {
+        let mut test = Fixture::new()
+            .seed(seed)
+            .item("Magic wire", vec2(0, 0))
+            .build();
+        test.trigger(vec2(0, 0), Trigger::DayBonus);
+        match test.inventory_count("Magic wire") {
+            1 => false,
+            count => {
+                assert_eq!(count, 2);
+                true
+            }
+        }
+    });
+}
+
+#[test]
+fn melter_sometimes_melts_tech() {
+    for_seeds(|seed| {
+        let mut test = Fixture::new()
+            .seed(seed)
+            .item("Melter", vec2(0, 0))
+            .item("Camera", vec2(1, 0))
+            .item("Sword", vec2(1, 1))
+            .item("Electric rod", vec2(0, 1))
+            .build();
+        test.trigger(vec2(0, 0), Trigger::DayBonus);
+        if test.inventory_len() == 4 {
+            assert_eq!(test.perm_damage_at(vec2(1, 1)), None);
+            false
+        } else {
+            // Either the camera or the rod has been melted
+            assert_eq!(test.inventory_len(), 3);
+            assert_eq!(test.perm_damage_at(vec2(1, 1)), Some(1));
+            if let Some(rod) = test.item_at(vec2(0, 1)) {
+                assert_eq!(rod, "Electric rod");
+                assert_eq!(test.perm_damage_at(vec2(0, 1)), Some(1));
+            }
+            true
+        }
+    });
+}
+
+#[test]
+fn phantom_grows_on_attack() {
+    let mut test = Fixture::new()
+        .item("Phantom", vec2(0, 0))
+        .enemy(vec2(1, 1), 5)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(4));
+    assert_eq!(test.perm_damage_at(vec2(0, 0)), Some(1));
+
+    test.model
+        .state
+        .borrow_mut()
+        .items
+        .iter_mut()
+        .for_each(|(_, item)| item.used = false);
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(2));
+}
+
+#[test]
+fn cursed_skull_crowns_on_top_row() {
+    let mut test = Fixture::new()
+        .item("Cursed skull", vec2(0, 1))
+        .item("Cursed skull", vec2(0, 0))
+        .build();
+    test.trigger(vec2(0, 1), Trigger::DayBonus);
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    assert_eq!(test.item_at(vec2(0, 1)).as_deref(), Some("King's skull"));
+    assert_eq!(test.item_at(vec2(0, 0)).as_deref(), Some("Cursed skull"));
+}
+
+#[test]
+fn cursed_skull_swaps_with_spooky() {
+    let mut test = Fixture::new()
+        .item("Cursed skull", vec2(0, 0))
+        .item("Ghost", vec2(1, 1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.item_at(vec2(0, 0)).as_deref(), Some("Ghost"));
+    assert_eq!(test.item_at(vec2(1, 1)).as_deref(), Some("Cursed skull"));
+}
+
+#[test]
+fn cursed_skull_glows_at_night() {
+    let mut test = Fixture::new().item("Cursed skull", vec2(0, 0)).build();
+    test.trigger(vec2(0, 0), Trigger::Night);
+    let state = test.model.state.borrow();
+    assert_eq!(state.grid.lights.get(&vec2(0, 0)), Some(&1));
+    assert_eq!(state.grid.lights.len(), 1);
+    assert!(state.visible_tiles.contains(&vec2(0, 0)));
+}
+
+#[test]
+fn king_skull_damages_all_enemies() {
+    let mut test = Fixture::new()
+        .grid(5)
+        .item("King's skull", vec2(0, 0))
+        .enemy(vec2(1, 1), 5)
+        .enemy(vec2(2, -2), 5)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(2));
+    assert_eq!(test.enemy_health(vec2(2, -2)), Some(2));
+}
+
+#[test]
+fn golden_lantern_lights_up_area() {
+    let mut test = Fixture::new()
+        .grid(5)
+        .item("Golden lantern", vec2(1, 1))
+        .build();
+    test.trigger(vec2(1, 1), Trigger::Active);
+    let state = test.model.state.borrow();
+    assert_eq!(state.grid.lights.len(), 9);
+    assert!(state.grid.lights.values().all(|&duration| duration == 3));
+    assert!(state.visible_tiles.contains(&vec2(2, 2)));
+    assert!(state.player.items.is_empty());
+}
+
+#[test]
+fn charming_staff_grows_when_observed() {
+    let mut test = Fixture::new()
+        .item("Charming staff", vec2(0, 0))
+        .visible(vec2(0, 0))
+        .enemy(vec2(1, 1), 5)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Night);
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(2));
+
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(3));
+
+    test.model.state.borrow_mut().visible_tiles.clear();
+    test.trigger(vec2(0, 0), Trigger::Night);
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(0));
+    test.trigger(vec2(0, 0), Trigger::Night);
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(0));
+}
+
+#[test]
+fn warp_portal_teleports_player() {
+    let mut test = Fixture::new()
+        .item("Warp portal", vec2(0, 0))
+        .item("Charming staff", vec2(1, 1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert!(matches!(test.model.phase, Phase::Portal { .. }));
+
+    test.input(PlayerInput::Tile(vec2(1, 1)));
+    assert!(matches!(test.model.phase, Phase::Player));
+    assert_eq!(test.player_position(), vec2(1, 1));
+    assert_eq!(
+        test.item_at(vec2(-1, -1)).as_deref(),
+        Some("Charming staff")
+    );
+}
+
+#[test]
+fn solitude_grows_alone() {
+    let mut test = Fixture::new()
+        .item("Solitude", vec2(0, 0))
+        .enemy(vec2(1, 1), 10)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(4));
+
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(6));
+}
+
+#[test]
+fn solitude_shrinks_in_company() {
+    let mut test = Fixture::new()
+        .item("Solitude", vec2(0, 0))
+        .item("Sword", vec2(1, 0))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(1));
+}
+
+#[test]
+fn invalid_selection_is_reported() {
+    let mut test = Fixture::new().build();
+    test.model.phase = Phase::Select {
+        options: vec![],
+        extra_items: 0,
+    };
+    test.model.player_action(PlayerInput::SelectItem(2));
+    assert!(test.model.events.iter().any(|event| matches!(
+        event,
+        ModelEvent::Error(ModelError::InvalidSelection { .. })
+    )));
+    assert!(matches!(test.model.phase, Phase::Select { .. }));
+}
+
+#[test]
+fn ghoul_approaches_player() {
+    let mut test = Fixture::new()
+        .grid(5)
+        .player(vec2(-2, 0))
+        .enemy_kind("Ghoul", vec2(2, 0), 4)
+        .build();
+    test.enemy_trigger(vec2(2, 0), EnemyTrigger::Night);
+    assert!(test.entity_at(vec2(2, 0)).is_none());
+    assert!(test.entity_at(vec2(1, 0)).is_some());
+}
+
+#[test]
+fn golem_shield_absorbs_damage() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .enemy_kind("Golem", vec2(1, 1), 4)
+        .build();
+    test.enemy_trigger(vec2(1, 1), EnemyTrigger::Night);
+    assert_eq!(test.entity_at(vec2(1, 1)).unwrap().1.shield, 2);
+
+    test.trigger(vec2(0, 0), Trigger::Active);
+    let (_, golem) = test.entity_at(vec2(1, 1)).unwrap();
+    assert_eq!(golem.shield, 0);
+    assert_eq!(golem.health.value(), 4);
+}
+
+#[test]
+fn shaman_heals_allies() {
+    let mut test = Fixture::new()
+        .enemy_kind("Shaman", vec2(0, 0), 3)
+        .enemy(vec2(1, 0), 5)
+        .enemy(vec2(1, 1), 5)
+        .build();
+    for position in [vec2(1, 0), vec2(1, 1)] {
+        test.model
+            .state
+            .borrow_mut()
+            .entities
+            .iter_mut()
+            .find(|(_, entity)| entity.position == position)
+            .unwrap()
+            .1
+            .damage(3);
+    }
+    test.enemy_trigger(vec2(0, 0), EnemyTrigger::Day);
+    assert_eq!(test.enemy_health(vec2(1, 0)), Some(4));
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(4));
+}
+
+#[test]
+fn imp_corrupts_weapons() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .enemy_kind("Imp", vec2(1, 0), 3)
+        .build();
+    test.enemy_trigger(vec2(1, 0), EnemyTrigger::Night);
+    assert_eq!(test.perm_damage_at(vec2(0, 0)), Some(-1));
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(1));
+}
+
+#[test]
+fn gremlin_sometimes_destroys_items() {
+    for_seeds(|seed| {
+        let mut test = Fixture::new()
+            .seed(seed)
+            .item("Sword", vec2(0, 0))
+            .enemy_kind("Gremlin", vec2(1, 0), 3)
+            .build();
+        test.enemy_trigger(vec2(1, 0), EnemyTrigger::Night);
+        test.item_at(vec2(0, 0)).is_none()
+    });
+}
+
+#[test]
+fn rat_king_spawns_rats() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .enemy_kind("Rat king", vec2(1, 1), 2)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_count("Rat king"), 0);
+    assert_eq!(test.enemy_count("Rat"), 2);
+}
+
+#[test]
+fn script_sees_nearest_enemy_health() {
+    let script = r#"
+        pub fn active(item) {
+            let enemy = item.nearest_enemy();
+            if let Some(enemy) = enemy {
+                let double = enemy.health * 2 < enemy.max_health;
+                item.damage(Target::Nearest, |stats| if double { stats.damage * 2 } else { stats.damage });
+            }
+        }
+    "#;
+    let mut test = Fixture::new()
+        .grid(5)
+        .custom_item("Executioner", 2, script)
+        .item("Executioner", vec2(0, 0))
+        .enemy(vec2(1, 0), 20)
+        .enemy(vec2(2, 2), 20)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 0)), Some(18));
+    test.model
+        .state
+        .borrow_mut()
+        .entities
+        .iter_mut()
+        .filter(|(_, entity)| entity.position == vec2(1, 0))
+        .for_each(|(_, entity)| entity.health.change(-10));
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 0)), Some(4));
+    assert_eq!(test.enemy_health(vec2(2, 2)), Some(20));
+}
+
+#[test]
+fn script_sees_player_and_enemies() {
+    let script = r#"
+        pub fn day_bonus(item) {
+            if let Some(player) = item.player_position() {
+                if item.distance_to(player) <= 1 {
+                    item.bonus(Stats::new().with_damage(1), false);
+                }
+            }
+            let enemies = item.enemies();
+            item.bonus(Stats::new().with_damage(enemies.len()), false);
+            for enemy in enemies {
+                if enemy.kind != "Dummy" {
+                    item.destroy();
+                }
+            }
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Watcher", 1, script)
+        .item("Watcher", vec2(0, 0))
+        .item("Watcher", vec2(1, 1))
+        .enemy(vec2(1, -1), 5)
+        .enemy(vec2(-1, 1), 5)
+        .player(vec2(-1, -1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    test.trigger(vec2(1, 1), Trigger::DayBonus);
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(4));
+    assert_eq!(test.damage_at(vec2(1, 1)), Some(3));
+}
+
+/// A script that hits the target with the item's damage.
+fn weapon_script(target: &str) -> String {
+    format!("pub fn active(item) {{ item.damage({target}, |stats| stats.damage); }}")
+}
+
+#[test]
+fn weakest_and_strongest_break_ties_by_distance() {
+    let mut test = Fixture::new()
+        .grid(5)
+        .custom_item("Weak hunter", 1, weapon_script("Target::Weakest"))
+        .custom_item("Strong hunter", 1, weapon_script("Target::Strongest"))
+        .item("Weak hunter", vec2(0, 0))
+        .item("Strong hunter", vec2(-2, -2))
+        .enemy(vec2(2, 2), 3)
+        .enemy(vec2(1, 0), 3)
+        .enemy(vec2(-2, 2), 5)
+        .enemy(vec2(-1, 0), 5)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 0)), Some(2));
+    assert_eq!(test.enemy_health(vec2(2, 2)), Some(3));
+    test.trigger(vec2(-2, -2), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(-1, 0)), Some(4));
+    assert_eq!(test.enemy_health(vec2(-2, 2)), Some(5));
+}
+
+#[test]
+fn farthest_and_position_targets() {
+    let mut test = Fixture::new()
+        .grid(5)
+        .custom_item("Sniper", 1, weapon_script("Target::Farthest"))
+        .custom_item(
+            "Mortar",
+            1,
+            weapon_script("Target::AtPosition(Position { x: 1, y: 1 })"),
+        )
+        .item("Sniper", vec2(0, 0))
+        .item("Mortar", vec2(0, -2))
+        .enemy(vec2(2, -2), 5)
+        .enemy(vec2(-2, 2), 5)
+        .enemy(vec2(1, 1), 5)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    // Equally far, the leftmost one is chosen
+    assert_eq!(test.enemy_health(vec2(-2, 2)), Some(4));
+    assert_eq!(test.enemy_health(vec2(2, -2)), Some(5));
+    test.trigger(vec2(0, -2), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(4));
+}
+
+#[test]
+fn line_and_row_targets() {
+    let mut test = Fixture::new()
+        .grid(5)
+        .custom_item(
+            "Spear",
+            1,
+            weapon_script("Target::InLine(Position { x: 1, y: 1 })"),
+        )
+        .custom_item("Wave", 1, weapon_script("Target::AllInRow"))
+        .item("Spear", vec2(-1, -1))
+        .item("Wave", vec2(0, 0))
+        .player(vec2(0, 2))
+        .enemy(vec2(-1, 0), 5)
+        .enemy(vec2(2, 2), 5)
+        .enemy(vec2(-2, 0), 5)
+        .enemy(vec2(2, 0), 5)
+        .build();
+    test.trigger(vec2(-1, -1), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(2, 2)), Some(4));
+    assert_eq!(test.enemy_health(vec2(-1, 0)), Some(5));
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(-1, 0)), Some(4));
+    assert_eq!(test.enemy_health(vec2(-2, 0)), Some(4));
+    assert_eq!(test.enemy_health(vec2(2, 0)), Some(4));
+    assert_eq!(test.enemy_health(vec2(2, 2)), Some(4));
+}
+
+#[test]
+fn observed_targets() {
+    let script = r#"
+        pub fn active(item) {
+            item.damage(Target::Unobserved, |stats| stats.damage);
+            item.damage_all_nearby(Some(2), Some(Target::Observed), |stats| stats.damage * 2);
+        }
+    "#;
+    let mut test = Fixture::new()
+        .grid(5)
+        .custom_item("Lens", 1, script)
+        .item("Lens", vec2(0, 0))
+        .enemy(vec2(1, 0), 5)
+        .enemy(vec2(2, 2), 5)
+        .enemy(vec2(-1, 1), 5)
+        .visible(vec2(2, 2))
+        .visible(vec2(-1, 1))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 0)), Some(4));
+    assert_eq!(test.enemy_health(vec2(2, 2)), Some(3));
+    assert_eq!(test.enemy_health(vec2(-1, 1)), Some(3));
+}
+
+/// Counts the activations in the memory.
+const COUNTER_SCRIPT: &str = r#"
+    pub fn init(memory) {
+        memory.set("count", 10);
+    }
+
+    pub fn active(item) {
+        item.memory.add("count", 1);
+    }
+
+    pub fn night(item) {
+        item.duplicate();
+    }
+"#;
+
+#[test]
+fn memory_persists_between_calls() {
+    let mut test = Fixture::new()
+        .custom_item("Counter", 1, COUNTER_SCRIPT)
+        .item("Counter", vec2(0, 0))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    test.trigger(vec2(0, 0), Trigger::Active);
+    let count = test.with_item(vec2(0, 0), |item| item.memory.get("count"));
+    assert_eq!(count, Some(12));
+}
+
+#[test]
+fn memory_is_copied_to_duplicates() {
+    let mut test = Fixture::new()
+        .custom_item("Counter", 1, COUNTER_SCRIPT)
+        .item("Counter", vec2(0, 0))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    test.trigger(vec2(0, 0), Trigger::Night);
+    let state = test.model.state.borrow();
+    let counts: Vec<_> = state
+        .player
+        .items
+        .iter()
+        .map(|(_, item)| item.memory.get("count"))
+        .collect();
+    assert_eq!(counts, vec![Some(11), Some(11)]);
+}
+
+#[test]
+fn memory_is_kept_on_transform() {
+    let script = r#"
+        pub fn active(item) {
+            item.memory.set("souls", 3);
+            item.turn_into("Sword");
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Soul jar", 1, script)
+        .item("Soul jar", vec2(0, 0))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.item_at(vec2(0, 0)).as_deref(), Some("Sword"));
+    let souls = test.with_item(vec2(0, 0), |item| item.memory.get("souls"));
+    assert_eq!(souls, Some(3));
+}
+
+#[test]
+fn duplicate_triggers_on_placed() {
+    let script = r#"
+        pub fn night(item) {
+            if item.memory.get("placed").is_none() {
+                item.duplicate();
+            }
+        }
+
+        pub fn on_placed(item) {
+            item.memory.add("placed", 1);
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Seed", 1, script)
+        .item("Seed", vec2(0, 0))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Night);
+    let state = test.model.state.borrow();
+    let placed: Vec<_> = state
+        .player
+        .items
+        .iter()
+        .map(|(_, item)| item.memory.get("placed"))
+        .collect();
+    assert_eq!(placed, vec![None, Some(1)]);
+}
+
+#[test]
+fn destroyed_item_resolves_before_removal() {
+    let script = r#"
+        pub fn active(item) {
+            item.destroy();
+        }
+
+        pub fn on_destroyed(item) {
+            item.damage(Target::Nearest, |stats| stats.damage);
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Bomb", 3, script)
+        .item("Bomb", vec2(0, 0))
+        .enemy(vec2(1, 1), 5)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(2));
+    assert_eq!(test.item_at(vec2(0, 0)), None);
+    assert_eq!(test.inventory_len(), 0);
+}
+
+#[test]
+fn items_react_to_killed_enemies() {
+    let script = r#"
+        pub fn on_enemy_killed(item) {
+            item.bonus(Stats::new().with_damage(1), true);
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Trophy", 0, script)
+        .item("Sword", vec2(0, 0))
+        .item("Trophy", vec2(1, -1))
+        .enemy(vec2(1, 1), 2)
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), None);
+    assert_eq!(test.perm_damage_at(vec2(1, -1)), Some(1));
+}
+
+#[test]
+fn items_react_to_acquired_items() {
+    let collector = r#"
+        pub fn on_item_acquired(item) {
+            item.bonus(Stats::new().with_damage(1), true);
+        }
+    "#;
+    let giver = r#"
+        pub fn active(item) {
+            item.get_new_item(None);
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Collector", 0, collector)
+        .custom_item("Giver", 0, giver)
+        .item("Collector", vec2(0, 0))
+        .item("Giver", vec2(1, 1))
+        .build();
+    test.trigger(vec2(1, 1), Trigger::Active);
+    assert_eq!(test.inventory_len(), 3);
+    assert_eq!(test.perm_damage_at(vec2(0, 0)), Some(1));
+}
+
+#[test]
+fn committed_vision_triggers_on_observed() {
+    let script = r#"
+        pub fn on_observed(item) {
+            item.bonus(Stats::new().with_damage(1), true);
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Shy", 0, script)
+        .item("Shy", vec2(1, -1))
+        .item("Shy", vec2(1, 1))
+        .build();
+    test.model.phase = Phase::Vision;
+    // Looking around does not count
+    test.input(PlayerInput::Vision {
+        pos: vec2(-1, 0),
+        commit: false,
+    });
+    test.input(PlayerInput::Vision {
+        pos: vec2(0, -1),
+        commit: true,
+    });
+    assert_eq!(test.perm_damage_at(vec2(1, -1)), Some(1));
+    assert_eq!(test.perm_damage_at(vec2(1, 1)), None);
+}
+
+/// The sword asset with a different script.
+fn reloaded_sword(script: &str) -> ItemAsset {
+    let mut asset = ItemAsset::load_headless(assets_path().join("items").join("sword"))
+        .expect("failed to load the sword");
+    asset.script = Some(script.to_owned());
+    asset
+}
+
+#[test]
+fn reloaded_item_replaces_kind() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .enemy(vec2(1, 1), 10)
+        .build();
+    test.with_item(vec2(0, 0), |item| {
+        item.perm_stats.damage = Some(1);
+        item.memory.set("kills", 2);
+    });
+
+    let script = r#"
+        pub fn active(item) {
+            item.damage(Target::Nearest, |stats| stats.damage * 2);
+        }
+    "#;
+    test.model
+        .reload_item(reloaded_sword(script))
+        .expect("failed to reload");
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(4));
+    assert_eq!(
+        test.with_item(vec2(0, 0), |item| item.memory.get("kills")),
+        Some(2)
+    );
+}
+
+#[test]
+fn broken_reload_keeps_old_kind() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .enemy(vec2(1, 1), 10)
+        .build();
+    let report = test
+        .model
+        .reload_item(reloaded_sword("pub fn active(item) { item.damage( }"))
+        .expect_err("the script should not compile");
+    assert!(report.has_errors());
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(8));
+}
+
+/// The library with a single `stats` module.
+fn stats_library(module: &str) -> ScriptLibrary {
+    ScriptLibrary {
+        modules: vec![("stats".to_owned(), module.to_owned())],
+    }
+}
+
+#[test]
+fn reloaded_library_recompiles_dependent_items() {
+    let mut test = Fixture::new()
+        .item("Solitude", vec2(0, 0))
+        .item("Sword", vec2(1, 0))
+        .build();
+    let report = test
+        .model
+        .reload_library(stats_library("pub fn lose_damage(item, amount) {"))
+        .expect_err("the library should not compile");
+    assert!(report.has_errors());
+
+    let module = r#"
+        pub fn lose_damage(item, amount) {
+            item.bonus(Stats::new().with_damage(amount), true);
+        }
+    "#;
+    let names = test
+        .model
+        .reload_library(stats_library(module))
+        .expect("failed to reload");
+    assert!(names.iter().any(|name| &**name == "Solitude"));
+    assert!(!names.iter().any(|name| &**name == "Sword"));
+    test.trigger(vec2(0, 0), Trigger::DayBonus);
+    assert_eq!(test.perm_damage_at(vec2(0, 0)), Some(1));
+}
+
+#[test]
+fn library_errors_point_at_the_module() {
+    let config =
+        Config::load_headless(assets_path().join("config.ron")).expect("failed to load config");
+    let mut items =
+        ItemAssets::load_headless(assets_path().join("items")).expect("failed to load items");
+    let enemies =
+        EnemyAssets::load_headless(assets_path().join("enemies")).expect("failed to load enemies");
+    items.library.modules.push((
+        "broken".to_owned(),
+        "pub fn ok() {\n    1\n}\n\npub fn broken( {\n}\n".to_owned(),
+    ));
+
+    let report = check_items(&config, &items, &enemies).expect("failed to check items");
+    let errors: Vec<_> = report
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .collect();
+    assert!(!errors.is_empty());
+    assert!(errors
+        .iter()
+        .any(|diagnostic| &*diagnostic.source == "_lib/broken.rn"
+            && diagnostic.location.map(|(line, _)| line) == Some(5)));
+}
+
+/// The script with a syntax error on the 5th line.
+const BROKEN_SCRIPT: &str = "pub fn active(item) {\n}\n\npub fn night(item) {\n    let = 1;\n}\n";
+
+/// All items, with the sword's script broken.
+fn broken_sword_assets() -> ItemAssets {
+    let mut items =
+        ItemAssets::load_headless(assets_path().join("items")).expect("failed to load items");
+    items.assets.get_mut("Sword").expect("no sword").script = Some(BROKEN_SCRIPT.to_owned());
+    items
+}
+
+#[test]
+fn syntax_errors_point_at_the_line() {
+    let config =
+        Config::load_headless(assets_path().join("config.ron")).expect("failed to load config");
+    let enemies =
+        EnemyAssets::load_headless(assets_path().join("enemies")).expect("failed to load enemies");
+    let report =
+        check_items(&config, &broken_sword_assets(), &enemies).expect("failed to check items");
+    let errors: Vec<_> = report
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            diagnostic.severity == Severity::Error && &*diagnostic.source == "Sword"
+        })
+        .collect();
+    assert!(!errors.is_empty());
+    let line_start = BROKEN_SCRIPT.find("    let").unwrap();
+    let span = errors[0].span.clone().expect("no span");
+    assert!(span.start >= line_start && span.end <= BROKEN_SCRIPT.len());
+    assert_eq!(errors[0].location.map(|(line, _)| line), Some(5));
+}
+
+#[test]
+fn strict_scripts_fail_the_run() {
+    let mut config =
+        Config::load_headless(assets_path().join("config.ron")).expect("failed to load config");
+    let enemies = Rc::new(
+        EnemyAssets::load_headless(assets_path().join("enemies")).expect("failed to load enemies"),
+    );
+    let items = Rc::new(broken_sword_assets());
+
+    config.strict_scripts = true;
+    assert!(Model::new(config.clone(), Rc::clone(&items), Rc::clone(&enemies), 0).is_err());
+
+    // Otherwise the script is replaced with an empty one
+    config.strict_scripts = false;
+    let model = Model::new(config, items, enemies, 0).expect("failed to start the run");
+    let state = model.state.borrow();
+    let sword = state
+        .all_items
+        .iter()
+        .find(|kind| &*kind.config.name == "Sword")
+        .expect("no sword");
+    assert!(!model.engine.handles(sword, "active"));
+    assert!(!model.engine.handles(sword, "night"));
+}
+
+#[test]
+fn unknown_item_names_are_reported() {
+    let config =
+        Config::load_headless(assets_path().join("config.ron")).expect("failed to load config");
+    let mut items =
+        ItemAssets::load_headless(assets_path().join("items")).expect("failed to load items");
+    let script = r#"
+        pub fn active(item) {
+            if item.count_items(Filter::Named("Sword")) > 1 {
+                item.turn_into("Excalibur");
+            }
+        }
+    "#;
+    items.assets.get_mut("Forge").expect("no forge").script = Some(script.to_owned());
+
+    let report = validate_items(&config, &items);
+    let errors: Vec<_> = report
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            diagnostic.severity == Severity::Error && &*diagnostic.source == "Forge"
+        })
+        .collect();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("Excalibur"));
+    let span = errors[0].span.clone().expect("no span");
+    assert_eq!(&script[span], "\"Excalibur\"");
+    assert_eq!(errors[0].location.map(|(line, _)| line), Some(4));
+}
+
+#[test]
+fn missing_item_files_are_reported() {
+    let path = std::env::temp_dir().join(format!("quantum_dungeon_items_{}", std::process::id()));
+    let sword = assets_path().join("items").join("sword");
+    std::fs::create_dir_all(path.join("sword")).expect("failed to create the item directory");
+    std::fs::write(path.join("_list.ron"), "[\"sword\"]").expect("failed to write the list");
+    for file in ["config.ron", "script.rn"] {
+        std::fs::copy(sword.join(file), path.join("sword").join(file))
+            .expect("failed to copy the item");
+    }
+    let items = ItemAssets::load_headless(&path);
+    std::fs::remove_dir_all(&path).expect("failed to clean up");
+    let items = items.expect("failed to load items");
+
+    let config =
+        Config::load_headless(assets_path().join("config.ron")).expect("failed to load config");
+    let report = validate_items(&config, &items);
+    let warnings: Vec<_> = report
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            diagnostic.severity == Severity::Warning && &*diagnostic.source == "Sword"
+        })
+        .map(|diagnostic| diagnostic.message.as_str())
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            "\"description.txt\" is missing",
+            "\"texture.png\" is missing"
+        ]
+    );
+}
+
+#[test]
+fn scripts_without_handlers_are_reported() {
+    let config =
+        Config::load_headless(assets_path().join("config.ron")).expect("failed to load config");
+    let mut items =
+        ItemAssets::load_headless(assets_path().join("items")).expect("failed to load items");
+    let enemies =
+        EnemyAssets::load_headless(assets_path().join("enemies")).expect("failed to load enemies");
+    items.assets.get_mut("Sword").expect("no sword").script =
+        Some("pub fn on_activate(item) {}\npub fn at_night(item) {}\n".to_owned());
+
+    let report = check_items(&config, &items, &enemies).expect("failed to check items");
+    assert!(report.diagnostics.iter().any(|diagnostic| {
+        diagnostic.severity == Severity::Warning
+            && &*diagnostic.source == "Sword"
+            && diagnostic
+                .message
+                .contains("does not handle any of the triggers")
+    }));
+}
+
+#[test]
+fn init_without_memory_is_reported() {
+    let config =
+        Config::load_headless(assets_path().join("config.ron")).expect("failed to load config");
+    let mut items =
+        ItemAssets::load_headless(assets_path().join("items")).expect("failed to load items");
+    let enemies =
+        EnemyAssets::load_headless(assets_path().join("enemies")).expect("failed to load enemies");
+    items.assets.get_mut("Sword").expect("no sword").script =
+        Some("pub fn active(item) {}\n\npub fn init() {}\n".to_owned());
+
+    let report = check_items(&config, &items, &enemies).expect("failed to check items");
+    assert!(report.diagnostics.iter().any(|diagnostic| {
+        diagnostic.severity == Severity::Error
+            && &*diagnostic.source == "Sword"
+            && diagnostic.message.contains("init(memory)")
+            && diagnostic.location == Some((3, 5))
+    }));
+}
+
+#[test]
+fn unknown_enemy_names_are_reported() {
+    let mut enemies =
+        EnemyAssets::load_headless(assets_path().join("enemies")).expect("failed to load enemies");
+    assert!(validate_enemies(&enemies).diagnostics.is_empty());
+
+    let rat_king = enemies.assets.get_mut("Rat king").expect("no rat king");
+    rat_king.script = Some("pub fn on_death(enemy) { enemy.spawn_minion(\"Mouse\"); }".to_owned());
+    let report = validate_enemies(&enemies);
+    assert!(report.has_errors());
+    assert!(report.diagnostics.iter().any(
+        |diagnostic| &*diagnostic.source == "Rat king" && diagnostic.message.contains("Mouse")
+    ));
+}
+
+#[test]
+fn infinite_loop_is_stopped() {
+    let mut test = Fixture::new()
+        .custom_item("Looper", 1, "pub fn active(item) { loop {} }")
+        .item("Looper", vec2(0, 0))
+        .item("Sword", vec2(1, 0))
+        .enemy(vec2(1, 1), 10)
+        .build();
+    test.model.events.clear();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert!(test.model.events.iter().any(|event| matches!(
+        event,
+        ModelEvent::Error(ModelError::Script { item, .. }) if &**item == "Looper"
+    )));
+
+    // The game goes on
+    test.trigger(vec2(1, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(1, 1)), Some(8));
+}
+
+#[test]
+fn effect_chain_is_cut_off() {
+    let ping = r#"
+        pub fn active(item) {
+            if let Some(other) = item.find_nearby(1, Filter::Named("Pong")) {
+                item.use_item(other);
+            }
+        }
+    "#;
+    let pong = r#"
+        pub fn active(item) {
+            if let Some(other) = item.find_nearby(1, Filter::Named("Ping")) {
+                item.use_item(other);
+            }
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Ping", 0, ping)
+        .custom_item("Pong", 0, pong)
+        .item("Ping", vec2(0, 0))
+        .item("Pong", vec2(1, 0))
+        .build();
+    test.model.config.max_effect_depth = 4;
+    test.model.events.clear();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert!(test.model.events.iter().any(|event| matches!(
+        event,
+        ModelEvent::Error(ModelError::EffectChain { depth: 4, .. })
+    )));
+    assert!(test.model.effect_queue_stack.is_empty());
+}
+
+#[test]
+fn queued_trigger_chain_is_cut_off() {
+    let hoarder = r#"
+        pub fn on_item_acquired(item) {
+            item.get_new_item(None);
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Hoarder", 0, hoarder)
+        .item("Hoarder", vec2(0, 0))
+        .build();
+    test.model.config.max_effect_depth = 4;
+    test.model.events.clear();
+    test.trigger(vec2(0, 0), Trigger::ItemAcquired);
+    assert!(test.model.events.iter().any(|event| matches!(
+        event,
+        ModelEvent::Error(ModelError::EffectChain { item, depth: 4 }) if &**item == "Hoarder"
+    )));
+    assert!(test.model.resolution_queue.is_empty());
+    assert_eq!(test.inventory_len(), 5);
+}
+
+/// Cancels the destruction of the items next to it.
+const WARD_SCRIPT: &str = r#"
+    pub fn react(item, effect) {
+        if effect.kind() == "Destroy" {
+            if let Some(target) = effect.target_item() {
+                if item.distance_to(target.position) <= 1 {
+                    effect.cancel();
+                }
+            }
+        }
+    }
+"#;
+
+#[test]
+fn ward_cancels_nearby_destruction() {
+    let bomb = "pub fn active(item) { item.destroy(); }";
+    let mut test = Fixture::new()
+        .custom_item("Ward", 0, WARD_SCRIPT)
+        .custom_item("Bomb", 0, bomb)
+        .item("Ward", vec2(0, 0))
+        .item("Bomb", vec2(1, 0))
+        .item("Bomb", vec2(2, 2))
+        .build();
+    test.trigger(vec2(1, 0), Trigger::Active);
+    test.trigger(vec2(2, 2), Trigger::Active);
+    assert_eq!(test.item_at(vec2(1, 0)).as_deref(), Some("Bomb"));
+    assert_eq!(test.item_at(vec2(2, 2)), None);
+}
+
+#[test]
+fn ward_cancels_gremlin_destruction() {
+    for seed in 0..20 {
+        let mut test = Fixture::new()
+            .seed(seed)
+            .custom_item("Ward", 0, WARD_SCRIPT)
+            .item("Ward", vec2(0, 0))
+            .item("Sword", vec2(0, 1))
+            .enemy_kind("Gremlin", vec2(1, 0), 3)
+            .build();
+        test.enemy_trigger(vec2(1, 0), EnemyTrigger::Night);
+        assert_eq!(test.item_at(vec2(0, 0)).as_deref(), Some("Ward"));
+        assert_eq!(test.item_at(vec2(0, 1)).as_deref(), Some("Sword"));
+    }
+}
+
+#[test]
+fn amplifiers_add_damage_in_order() {
+    let amplifier = r#"
+        pub fn react(item, effect) {
+            if effect.kind() == "Damage" {
+                effect.add_damage(1);
+            }
+        }
+    "#;
+    let limiter = r#"
+        pub fn react(item, effect) {
+            if let Some(enemy) = effect.target_entity() {
+                if enemy.health <= 7 {
+                    effect.cancel();
+                }
+            }
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Amplifier", 0, amplifier)
+        .custom_item("Limiter", 0, limiter)
+        .item("Amplifier", vec2(0, 2))
+        .item("Amplifier", vec2(0, 1))
+        .item("Sword", vec2(1, 0))
+        .item("Limiter", vec2(2, 2))
+        .enemy(vec2(2, 0), 10)
+        .build();
+    test.trigger(vec2(1, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(2, 0)), Some(6));
+
+    // The enemy is weak enough for the limiter now
+    for (_, item) in &mut test.model.state.borrow_mut().items {
+        item.used = false;
+    }
+    test.trigger(vec2(1, 0), Trigger::Active);
+    assert_eq!(test.enemy_health(vec2(2, 0)), Some(6));
+}
+
+/// Takes all the bonuses meant for the other items.
+const MIRROR_SCRIPT: &str = r#"
+    pub fn react(item, effect) {
+        if effect.kind() == "Bonus" {
+            if let Some(target) = effect.target_item() {
+                if target.position.x != item.position.x || target.position.y != item.position.y {
+                    effect.redirect(item);
+                }
+            }
+        }
+    }
+"#;
+
+#[test]
+fn mirror_redirects_bonuses() {
+    let booster = r#"
+        pub fn active(item) {
+            item.bonus_to_nearby(1, Filter::Named("Sword"), Stats::new().with_damage(3), true);
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Mirror", 0, MIRROR_SCRIPT)
+        .custom_item("Booster", 0, booster)
+        .item("Booster", vec2(0, 0))
+        .item("Sword", vec2(1, 0))
+        .item("Mirror", vec2(2, 2))
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    assert_eq!(test.perm_damage_at(vec2(1, 0)), None);
+    assert_eq!(test.perm_damage_at(vec2(2, 2)), Some(3));
+}
+
+#[test]
+fn mirror_redirects_imp_corruption() {
+    let mut test = Fixture::new()
+        .custom_item("Mirror", 0, MIRROR_SCRIPT)
+        .item("Sword", vec2(0, 0))
+        .item("Mirror", vec2(2, 2))
+        .enemy_kind("Imp", vec2(1, 0), 3)
+        .build();
+    test.enemy_trigger(vec2(1, 0), EnemyTrigger::Night);
+    assert_eq!(test.perm_damage_at(vec2(0, 0)), None);
+    assert_eq!(test.perm_damage_at(vec2(2, 2)), Some(-1));
+}
+
+#[test]
+fn composite_filters() {
+    let counter = r#"
+        pub fn active(item) {
+            item.memory.set("fire", item.count_items(Filter::Tag("fire")));
+            item.memory.set(
+                "magic_not_weapon",
+                item.count_items(Filter::All([
+                    Filter::Category(Category::Magic),
+                    Filter::not(Filter::Category(Category::Weapon)),
+                ])),
+            );
+            item.memory.set(
+                "sword_or_damage",
+                item.count_items(Filter::Any([Filter::Named("Sword"), Filter::HasStat(Stat::Damage)])),
+            );
+            item.memory.set("stashed", item.count_items(Filter::InInventory));
+            item.memory.set("on_board", item.count_items(Filter::OnBoard));
+        }
+    "#;
+    let mut test = Fixture::new()
+        .custom_item("Counter", 0, counter)
+        .item("Counter", vec2(0, 0))
+        .item("Fire scroll", vec2(1, 0))
+        .item("Forge", vec2(2, 0))
+        .stashed_item("Sword")
+        .stashed_item("Ultra speed shoes")
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+
+    let memory = test.with_item(vec2(0, 0), |item| item.memory.clone());
+    assert_eq!(memory.get("fire"), Some(2));
+    assert_eq!(memory.get("magic_not_weapon"), Some(1));
+    // Counter and Fire scroll have damage, Sword has both
+    assert_eq!(memory.get("sword_or_damage"), Some(3));
+    assert_eq!(memory.get("stashed"), Some(2));
+    assert_eq!(memory.get("on_board"), Some(3));
+}
+
+#[test]
+fn shop_offers_different_unlocked_items() {
+    let names = |test: &TestModel| -> Vec<String> {
+        test.model
+            .shop_options(100)
+            .into_iter()
+            .map(|kind| kind.config.name.to_string())
+            .collect()
+    };
+
+    let mut test = Fixture::new().build();
+    let options = names(&test);
+    assert_eq!(options.len(), options.iter().unique().count());
+    assert!(!options.iter().any(|name| name == "Radiation core"));
+    assert!(!options.iter().any(|name| name == "Spirit coin"));
+    assert!(!options.iter().any(|name| name == "King's skull"));
+
+    test.model.level = 2;
+    let options = names(&test);
+    assert!(options.iter().any(|name| name == "Radiation core"));
+
+    let test = Fixture::new().stashed_item("Chest").build();
+    let options = names(&test);
+    assert!(options.iter().any(|name| name == "Spirit coin"));
+}
+
+#[test]
+fn invalid_shop_configs_are_reported() {
+    let config =
+        Config::load_headless(assets_path().join("config.ron")).expect("failed to load config");
+    let mut items =
+        ItemAssets::load_headless(assets_path().join("items")).expect("failed to load items");
+    let shop = &mut items.assets.get_mut("Sword").expect("no sword").config.shop;
+    shop.weight = f32::NAN;
+    shop.min_level = 5;
+    shop.max_level = Some(2);
+
+    let report = validate_items(&config, &items);
+    let errors = report
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            diagnostic.severity == Severity::Error && &*diagnostic.source == "Sword"
+        })
+        .count();
+    assert_eq!(errors, 2);
+}
+
+#[test]
+fn buying_items_costs_gold() {
+    let mut test = Fixture::new().build();
+    let sword = test
+        .model
+        .state
+        .borrow()
+        .all_items
+        .iter()
+        .find(|kind| &*kind.config.name == "Sword")
+        .cloned()
+        .unwrap();
+    let price = sword.config.shop.price;
+    test.model.state.borrow_mut().player.gold = price + 1;
+
+    test.model.phase = Phase::Select {
+        options: vec![sword.clone()],
+        extra_items: 0,
+    };
+    test.model.player_action(PlayerInput::SelectItem(0));
+    assert_eq!(test.inventory_count("Sword"), 1);
+    assert_eq!(test.model.state.borrow().player.gold, 1);
+
+    test.model.phase = Phase::Select {
+        options: vec![sword],
+        extra_items: 0,
+    };
+    test.model.player_action(PlayerInput::SelectItem(0));
+    assert_eq!(test.inventory_count("Sword"), 1);
+    assert!(test.model.events.iter().any(|event| matches!(
+        event,
+        ModelEvent::Error(ModelError::NotEnoughGold { gold: 1, .. })
+    )));
+    assert!(matches!(test.model.phase, Phase::Select { .. }));
+}
+
+#[test]
+fn rerolls_cost_gold() {
+    let mut test = Fixture::new().build();
+    let reroll_price = test.model.config.economy.reroll_price;
+    test.model.state.borrow_mut().player.gold = reroll_price;
+    test.model.phase = Phase::Select {
+        options: vec![],
+        extra_items: 0,
+    };
+
+    test.model.player_action(PlayerInput::Reroll);
+    assert_eq!(test.model.state.borrow().player.gold, 0);
+    assert!(matches!(test.model.phase, Phase::Select { .. }));
+
+    test.model.player_action(PlayerInput::Reroll);
+    assert!(test.model.events.iter().any(|event| matches!(
+        event,
+        ModelEvent::Error(ModelError::NotEnoughGold { gold: 0, .. })
+    )));
+}
+
+#[test]
+fn selling_gives_half_the_price() {
+    let mut test = Fixture::new().item("Sword", vec2(0, 0)).build();
+    let board_item = test.board_item(vec2(0, 0)).unwrap();
+    let item_id = test.model.state.borrow().items[board_item].item_id;
+    let sell_price = test.with_item(vec2(0, 0), |item| item.kind.config.shop.sell_price());
+    test.model.phase = Phase::Select {
+        options: vec![],
+        extra_items: 0,
+    };
+
+    test.input(PlayerInput::SellItem(item_id));
+    assert_eq!(test.inventory_len(), 0);
+    assert_eq!(test.item_at(vec2(0, 0)), None);
+    assert_eq!(test.model.state.borrow().player.gold, sell_price);
+}
+
+#[test]
+fn copies_merge_into_higher_tier() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .stashed_item("Sword")
+        .stashed_item("Sword")
+        .item("Ultra speed shoes", vec2(1, 0))
+        .stashed_item("Ultra speed shoes")
+        .stashed_item("Ultra speed shoes")
+        .build();
+    test.model.merge_all();
+
+    assert_eq!(test.inventory_count("Sword"), 1);
+    assert_eq!(test.with_item(vec2(0, 0), |item| item.tier), 2);
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(3));
+
+    // The item's own bonus replaces the default one
+    assert_eq!(test.inventory_count("Ultra speed shoes"), 1);
+    let stats = test.with_item(vec2(1, 0), |item| item.current_stats());
+    assert_eq!(stats.moves, Some(4));
+    assert_eq!(stats.damage, None);
+}
+
+#[test]
+fn reloaded_item_keeps_tier_bonus() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .stashed_item("Sword")
+        .stashed_item("Sword")
+        .build();
+    test.model.merge_all();
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(3));
+
+    let script = std::fs::read_to_string(assets_path().join("items/sword/script.rn"))
+        .expect("failed to read the sword script");
+    test.model
+        .reload_item(reloaded_sword(&script))
+        .expect("failed to reload");
+    assert_eq!(test.with_item(vec2(0, 0), |item| item.tier), 2);
+    assert_eq!(test.damage_at(vec2(0, 0)), Some(3));
+}
+
+#[test]
+fn merging_needs_enough_copies() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .stashed_item("Sword")
+        .build();
+    test.model.config.merge.auto = false;
+    let board_item = test.board_item(vec2(0, 0)).unwrap();
+    let item_id = test.model.state.borrow().items[board_item].item_id;
+    test.model.phase = Phase::Select {
+        options: vec![],
+        extra_items: 0,
+    };
+
+    test.model.player_action(PlayerInput::MergeItem(item_id));
+    assert_eq!(test.inventory_count("Sword"), 2);
+    assert_eq!(test.with_item(vec2(0, 0), |item| item.tier), 1);
+
+    let sword = test.model.state.borrow().player.items[item_id].kind.clone();
+    let copy = test.model.engine.init_item(sword).unwrap();
+    test.model.state.borrow_mut().player.items.insert(copy);
+    test.model.player_action(PlayerInput::MergeItem(item_id));
+    assert_eq!(test.inventory_count("Sword"), 1);
+    assert_eq!(test.with_item(vec2(0, 0), |item| item.tier), 2);
+}
+
+#[test]
+fn extra_items_have_to_be_discarded() {
+    let mut test = Fixture::new()
+        .item("Sword", vec2(0, 0))
+        .stashed_item("Sword")
+        .build();
+    test.model.config.inventory_capacity = 2;
+    test.model.config.merge.auto = false;
+    let sword = test
+        .model
+        .state
+        .borrow()
+        .all_items
+        .iter()
+        .find(|kind| &*kind.config.name == "Sword")
+        .expect("no sword")
+        .clone();
+    let item = test.model.engine.init_item(sword).unwrap();
+    test.model.state.borrow_mut().player.items.insert(item);
+
+    test.model.update(r32(FIXED_DELTA_TIME));
+    assert!(matches!(test.model.phase, Phase::Discard { .. }));
+    assert!(test.model.is_waiting_for_input());
+
+    let board_item = test.board_item(vec2(0, 0)).unwrap();
+    let item_id = test.model.state.borrow().items[board_item].item_id;
+    test.model.player_action(PlayerInput::DiscardItem(item_id));
+    assert_eq!(test.inventory_len(), 2);
+    assert_eq!(test.item_at(vec2(0, 0)), None);
+    assert!(matches!(test.model.phase, Phase::Player));
+}
+
+#[test]
+fn discard_waits_for_the_effects() {
+    let mut test = Fixture::new()
+        .custom_item("Counter", 1, COUNTER_SCRIPT)
+        .item("Counter", vec2(0, 0))
+        .build();
+    test.model.config.inventory_capacity = 1;
+    test.model.config.merge.auto = false;
+    // The duplicate goes over the capacity before the counter is activated
+    test.model.resolve_all(Trigger::Night);
+    test.model.resolve_all(Trigger::Active);
+    while !test.model.wait_for_effects() {
+        test.model.update(r32(FIXED_DELTA_TIME));
+        assert!(matches!(test.model.phase, Phase::Player));
+    }
+    let count = test.with_item(vec2(0, 0), |item| item.memory.get("count"));
+    assert_eq!(count, Some(11));
+
+    test.model.update(r32(FIXED_DELTA_TIME));
+    assert!(matches!(test.model.phase, Phase::Discard { .. }));
+}
+
+#[test]
+fn items_are_placed_manually() {
+    let mut test = Fixture::new()
+        .stashed_item("Sword")
+        .stashed_item("Forge")
+        .build();
+    test.model.config.manual_placement = true;
+    test.model.next_level(false);
+    assert!(matches!(test.model.phase, Phase::Placement { .. }));
+    assert!(test.model.is_waiting_for_input());
+
+    let sword = test
+        .model
+        .state
+        .borrow()
+        .player
+        .items
+        .iter()
+        .find(|(_, item)| &*item.kind.config.name == "Sword")
+        .map(|(id, _)| id)
+        .unwrap();
+    let position = test
+        .model
+        .calculate_empty_space()
+        .into_iter()
+        .min_by_key(|pos| (pos.x, pos.y))
+        .unwrap();
+    test.model.player_action(PlayerInput::PlaceItem {
+        item: sword,
+        position,
+    });
+    assert_eq!(test.item_at(position).as_deref(), Some("Sword"));
+
+    // The rest is placed randomly
+    test.model.player_action(PlayerInput::Skip);
+    assert!(matches!(test.model.phase, Phase::Dawn { .. }));
+    assert_eq!(test.item_at(position).as_deref(), Some("Sword"));
+    assert!(test
+        .model
+        .state
+        .borrow()
+        .player
+        .items
+        .iter()
+        .all(|(_, item)| item.on_board.is_some()));
+}
+
+#[test]
+fn placement_waits_for_the_shifted_items() {
+    let drifter = r#"pub fn on_shifted(item) { item.memory.set("shifted", 1); }"#;
+    let mut test = Fixture::new()
+        .custom_item("Drifter", 0, drifter)
+        .item("Drifter", vec2(0, 0))
+        .stashed_item("Sword")
+        .build();
+    test.model.config.manual_placement = true;
+    test.model.resolve_all(Trigger::Shifted);
+    test.model.next_level(false);
+    assert!(matches!(test.model.phase, Phase::Placement { .. }));
+    assert!(!test.model.is_waiting_for_input());
+
+    let sword = test
+        .model
+        .state
+        .borrow()
+        .player
+        .items
+        .iter()
+        .find(|(_, item)| &*item.kind.config.name == "Sword")
+        .map(|(id, _)| id)
+        .unwrap();
+    let position = test
+        .model
+        .calculate_empty_space()
+        .into_iter()
+        .min_by_key(|pos| (pos.x, pos.y))
+        .unwrap();
+    test.model.player_action(PlayerInput::PlaceItem {
+        item: sword,
+        position,
+    });
+    assert_eq!(test.item_at(position), None);
+
+    test.resolve();
+    assert!(test.model.is_waiting_for_input());
+    let shifted = test.with_item(vec2(0, 0), |item| item.memory.get("shifted"));
+    assert_eq!(shifted, Some(1));
+    test.model.player_action(PlayerInput::PlaceItem {
+        item: sword,
+        position,
+    });
+    assert_eq!(test.item_at(position).as_deref(), Some("Sword"));
+}
+
+#[test]
+fn anchored_items_are_not_shifted() {
+    let mut test = Fixture::new()
+        .grid(5)
+        .custom_item("Anchor", 0, "pub fn active(item) { item.anchor(1); }")
+        .custom_item("Pillar", 0, "pub fn active(item) {}")
+        .item("Pillar", vec2(0, 0))
+        .item("Anchor", vec2(1, 0))
+        .item("Sword", vec2(1, 1))
+        .build();
+    // Anchored by the config
+    test.with_item(vec2(0, 0), |item| item.kind.config.anchored = true);
+    test.model
+        .state
+        .borrow_mut()
+        .grid
+        .lights
+        .insert(vec2(1, 1), 2);
+    test.trigger(vec2(1, 0), Trigger::Active);
+    let anchor = test.board_item(vec2(1, 0)).unwrap();
+
+    test.model.shift_everything();
+    assert_eq!(test.item_at(vec2(1, 0)).as_deref(), Some("Anchor"));
+    // Only for a single shift
+    assert_eq!(test.model.state.borrow().items[anchor].anchored, 0);
+
+    for _ in 0..10 {
+        test.model.shift_everything();
+        assert_eq!(test.item_at(vec2(0, 0)).as_deref(), Some("Pillar"));
+        assert_eq!(test.item_at(vec2(1, 1)).as_deref(), Some("Sword"));
+    }
+}
+
+/// Records the order of the level triggers in the memory.
+const LOGGER_SCRIPT: &str = r#"
+    fn log(item, key) {
+        item.memory.set(key, item.memory.add("step", 1));
+    }
+
+    pub fn on_level_end(item) {
+        log(item, "end");
+        item.bonus(Stats::damage(1), true);
+    }
+
+    pub fn on_shifted(item) {
+        log(item, "shifted");
+    }
+
+    pub fn on_level_start(item) {
+        log(item, "start");
+    }
+"#;
+
+#[test]
+fn level_triggers_are_in_order() {
+    let mut shifted_runs = 0;
+    for seed in 0..20 {
+        let mut test = Fixture::new()
+            .seed(seed)
+            .custom_item("Logger", 1, LOGGER_SCRIPT)
+            .item("Logger", vec2(0, 0))
+            .build();
+        test.model.finish_level(true);
+        assert!(matches!(test.model.phase, Phase::LevelFinished { .. }));
+
+        // The level is not left until the items are done with its end
+        while !test.model.wait_for_effects() {
+            test.model.update(r32(FIXED_DELTA_TIME));
+            assert!(matches!(test.model.phase, Phase::LevelFinished { .. }));
+        }
+        for _ in 0..MAX_STEPS {
+            if test.model.level > 0 {
+                break;
+            }
+            test.model.update(r32(FIXED_DELTA_TIME));
+        }
+        assert_eq!(test.model.level, 1);
+        test.resolve();
+
+        let state = test.model.state.borrow();
+        let memory = &state
+            .player
+            .items
+            .iter()
+            .find(|(_, item)| &*item.kind.config.name == "Logger")
+            .expect("no logger")
+            .1
+            .memory;
+        let (end, start) = (memory.get("end"), memory.get("start"));
+        assert_eq!(end, Some(1));
+        match memory.get("shifted") {
+            // Might have been shifted to the same position
+            None => assert_eq!(start, Some(2)),
+            Some(shifted) => {
+                assert_eq!(shifted, 2);
+                assert_eq!(start, Some(3));
+                shifted_runs += 1;
+            }
+        }
+    }
+    assert!(shifted_runs > 0, "the logger has never been shifted");
+}
+
+#[test]
+fn post_vision_waits_for_the_observed_items() {
+    let script = "pub fn on_observed(item) { item.bonus(Stats::damage(1), true); }";
+    let mut test = Fixture::new()
+        .custom_item("Watcher", 1, script)
+        .item("Watcher", vec2(0, 0))
+        .build();
+    test.model.phase = Phase::PostVision {
+        timer: Lifetime::new_max(r32(0.0)),
+    };
+    test.model.resolve_all(Trigger::Observed);
+    while !test.model.wait_for_effects() {
+        test.model.update(r32(FIXED_DELTA_TIME));
+        assert!(matches!(test.model.phase, Phase::PostVision { .. }));
+    }
+    assert_eq!(test.perm_damage_at(vec2(0, 0)), Some(1));
+    for _ in 0..MAX_STEPS {
+        if !matches!(test.model.phase, Phase::PostVision { .. }) {
+            return;
+        }
+        test.model.update(r32(FIXED_DELTA_TIME));
+    }
+    panic!("the shop has not been opened");
+}
+
+#[test]
+fn saved_run_is_continued() {
+    let mut test = Fixture::new()
+        .seed(3)
+        .player(vec2(1, -1))
+        .custom_item("Counter", 1, COUNTER_SCRIPT)
+        .item("Counter", vec2(0, 0))
+        .item("Sword", vec2(1, 1))
+        .stashed_item("Forge")
+        .build();
+    test.trigger(vec2(0, 0), Trigger::Active);
+    test.with_item(vec2(1, 1), |item| {
+        item.tier = 2;
+        item.perm_stats.damage = Some(3);
+    });
+    test.model.state.borrow_mut().player.gold = 7;
+
+    let saved = ron::to_string(&test.model.save_run()).expect("failed to serialize the run");
+    let saved: SavedRun = ron::from_str(&saved).expect("failed to deserialize the run");
+    let loaded = Model::load(
+        test.model.item_assets.clone(),
+        test.model.enemy_assets.clone(),
+        saved,
+    )
+    .expect("failed to load the run");
+    let loaded = TestModel { model: loaded };
+
+    assert_eq!(loaded.player_position(), vec2(1, -1));
+    {
+        let (state, loaded_state) = (test.model.state.borrow(), loaded.model.state.borrow());
+        let (player, loaded_player) = (&state.player, &loaded_state.player);
+        assert_eq!(loaded_player.gold, 7);
+        assert_eq!(loaded_player.hearts, player.hearts);
+        assert_eq!(loaded_player.moves_left, player.moves_left);
+        assert_eq!(loaded_player.turns_left, player.turns_left);
+    }
+    assert_eq!(loaded.inventory_len(), 3);
+    assert_eq!(loaded.inventory_count("Forge"), 1);
+
+    assert_eq!(loaded.item_at(vec2(0, 0)).as_deref(), Some("Counter"));
+    let count = loaded.with_item(vec2(0, 0), |item| item.memory.get("count"));
+    assert_eq!(count, Some(11));
+    assert_eq!(loaded.item_at(vec2(1, 1)).as_deref(), Some("Sword"));
+    assert_eq!(loaded.with_item(vec2(1, 1), |item| item.tier), 2);
+    assert_eq!(loaded.perm_damage_at(vec2(1, 1)), Some(3));
+
+    // Both continue with the same randomness
+    let next = |model: &Model| -> u64 { model.state.borrow().rng.borrow_mut().gen() };
+    assert_eq!(next(&loaded.model), next(&test.model));
+}
+
+/// The maximum number of updates for a whole run played by the bot.
+const RUN_STEPS: usize = 20_000;
+
+fn simulation(seed: u64) -> Simulation {
+    Simulation::load(&assets_path().join("config.ron"), &assets_path(), seed)
+        .expect("failed to start the simulation")
+}
+
+/// The state of the run to compare the runs by.
+fn snapshot(model: &mut Model) -> String {
+    ron::to_string(&model.save_run()).expect("failed to serialize the run")
+}
+
+#[test]
+fn replay_reproduces_the_run() {
+    let mut original = simulation(7);
+    original.play(&mut RandomBot::new(7), RUN_STEPS);
+    original.run_until_input(MAX_STEPS);
+    let replay = original.model.replay.clone();
+    assert!(!replay.inputs.is_empty());
+
+    let mut replayed = simulation(replay.seed);
+    assert!(replayed.play_back(replay, RUN_STEPS + MAX_STEPS));
+    assert_eq!(replayed.model.ticks, original.model.ticks);
+    assert_eq!(replayed.model.score, original.model.score);
+    assert_eq!(snapshot(&mut replayed.model), snapshot(&mut original.model));
+}
+
+#[test]
+fn runs_with_the_same_seed_are_the_same() {
+    let run = || {
+        let mut simulation = simulation(11);
+        simulation.play(&mut RandomBot::new(11), RUN_STEPS);
+        simulation
+    };
+    let (mut a, mut b) = (run(), run());
+    assert_eq!(a.steps, b.steps);
+    assert_eq!(a.model.level, b.model.level);
+    assert_eq!(a.model.score, b.model.score);
+    assert_eq!(snapshot(&mut a.model), snapshot(&mut b.model));
+}
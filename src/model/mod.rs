@@ -1,13 +1,24 @@
 mod animation;
+mod diagnostic;
 mod effect;
+mod enemy;
 mod engine;
 mod entity;
+mod error;
+mod event;
 mod grid;
 mod item;
 mod logic;
 mod player;
+mod replay;
+mod save;
+mod validate;
 
-pub use self::{animation::*, entity::*, grid::*, item::*, player::*};
+pub use self::engine::check_items;
+pub use self::{
+    animation::*, diagnostic::*, enemy::*, entity::*, error::*, event::*, grid::*, item::*,
+    player::*, replay::*, save::*, validate::*,
+};
 use self::{effect::*, engine::Engine};
 
 use crate::prelude::*;
@@ -19,13 +30,24 @@ pub type ScriptFunction = rune::runtime::Function;
 pub type Time = R32;
 pub type Coord = i64;
 pub type Score = u64;
+pub type Gold = u64;
+
+/// The fixed time step of a single model update.
+/// The model has to be updated with the same step for the replays to be deterministic.
+pub const FIXED_DELTA_TIME: f32 = 1.0 / 60.0;
 
 pub struct Model {
-    pub assets: Rc<Assets>,
     pub item_assets: Rc<ItemAssets>,
+    pub enemy_assets: Rc<EnemyAssets>,
     pub config: Config,
+    /// The seed used to initialize the random number generator of the run.
+    pub seed: u64,
     engine: Engine,
     pub state: Rc<RefCell<ModelState>>,
+    /// The number of updates performed so far.
+    pub ticks: usize,
+    /// Recording of the run.
+    pub replay: Replay,
     pub level: usize,
     pub turn: usize,
     pub score: Score,
@@ -37,11 +59,19 @@ pub struct Model {
     pub resolving_items: Collection<ItemResolving>,
     pub resolved_items: Collection<ItemResolved>,
 
-    pub resolution_queue: VecDeque<(Id, Trigger)>,
+    /// Items waiting to resolve the trigger,
+    /// with the depth of the effect chain they were queued from.
+    pub resolution_queue: VecDeque<(Id, Trigger, usize)>,
+    /// The depth of the effect chain the currently resolving trigger was queued from.
+    pub chain_depth: usize,
+    /// Enemies waiting to act, resolved after the items.
+    pub enemy_queue: VecDeque<(Id, EnemyTrigger)>,
     /// The stack of effect queues.
     pub effect_queue_stack: Vec<VecDeque<QueuedEffect>>,
     /// Effects produced by scripts. Should be consumed after the script is executed and moved to the queue.
     pub side_effects: Rc<RefCell<Vec<Effect>>>,
+    /// Events for the outside world (sounds, feedback). Should be drained by the owner of the model.
+    pub events: Vec<ModelEvent>,
 }
 
 #[derive(Debug, HasId)]
@@ -61,15 +91,24 @@ pub struct ItemResolved {
 /// The stuff accessible from within the scripts.
 pub struct ModelState {
     pub all_items: Vec<ItemKind>,
+    pub all_enemies: Vec<EnemyKind>,
     pub grid: Grid,
     pub player: Player,
     pub items: Arena<BoardItem>,
     pub entities: Arena<Entity>,
     pub visible_tiles: HashSet<vec2<Coord>>,
+    /// The source of all randomness in the run.
+    pub rng: RefCell<StdRng>,
 }
 
 #[derive(Debug, Clone)]
 pub enum Phase {
+    /// Place the items on the board before the level starts.
+    Placement {
+        first_level: bool,
+        /// The items put on the board during the placement.
+        placed: Vec<Id>,
+    },
     /// Level transition.
     LevelStarting { timer: Lifetime },
     /// Resolve night effects.
@@ -100,6 +139,11 @@ pub enum Phase {
         /// Phase to go to after the teleport.
         next_phase: Box<Phase>,
     },
+    /// The inventory is over capacity, drop some items.
+    Discard {
+        /// Phase to go to after enough items have been discarded.
+        next_phase: Box<Phase>,
+    },
     /// Player sets their look direction.
     Vision,
     /// Vision has beet set, visualize.
@@ -116,72 +160,106 @@ pub enum Phase {
 }
 
 impl Model {
-    pub fn new(assets: Rc<Assets>, config: Config, item_assets: Rc<ItemAssets>) -> Self {
+    pub fn new(
+        config: Config,
+        item_assets: Rc<ItemAssets>,
+        enemy_assets: Rc<EnemyAssets>,
+        seed: u64,
+    ) -> anyhow::Result<Self> {
+        log::info!("Starting a new run with seed {}", seed);
         let state = ModelState {
             all_items: vec![], // Initialized after engine
+            all_enemies: vec![],
             grid: Grid::new(3),
-            player: Player::new(),
+            player: Player {
+                gold: config.economy.starting_gold,
+                ..Player::new()
+            },
             items: Arena::new(),
             entities: [Entity {
                 position: vec2(0, 0),
                 fraction: Fraction::Player,
                 health: Health::new_max(100),
+                shield: 0,
                 look_dir: vec2(0, 0),
                 kind: EntityKind::Player,
             }]
             .into_iter()
             .collect(),
             visible_tiles: HashSet::new(),
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
         };
         let state = Rc::new(RefCell::new(state));
 
         // TODO: maybe mpsc or smth
         let side_effects = Rc::new(RefCell::new(Vec::new()));
 
-        // TODO: handle errors maybe
-        let engine = Engine::new(Rc::clone(&state), Rc::clone(&side_effects))
-            .expect("Script engine initialization failed");
+        let engine = Engine::new(
+            Rc::clone(&state),
+            Rc::clone(&side_effects),
+            config.script_budget,
+        )
+        .context("when initializing the script engine")?;
         let all_items = engine
-            .compile_items(&item_assets)
-            .expect("Item compilation failed");
+            .compile_items(&item_assets, config.strict_scripts)
+            .context("when compiling items")?;
+        let all_enemies = engine
+            .compile_enemies(&enemy_assets, config.strict_scripts)
+            .context("when compiling enemies")?;
 
         {
             // Initialize player items
             let player_items = &mut state.borrow_mut().player.items;
             for item in &config.starting_items {
-                match all_items.iter().find(|kind| *kind.config.name == **item) {
-                    Some(item) => {
-                        let item = engine
-                            .init_item(item.clone())
-                            .expect("Item initialization failed");
-                        player_items.insert(item);
-                    }
-                    None => {
-                        panic!("Unknown item {}", item);
-                    }
-                };
+                let kind = all_items
+                    .iter()
+                    .find(|kind| *kind.config.name == **item)
+                    .with_context(|| format!("unknown starting item {}", item))?;
+                let item = engine
+                    .init_item(kind.clone())
+                    .with_context(|| format!("when initializing item {}", item))?;
+                player_items.insert(item);
             }
         }
 
-        state.borrow_mut().all_items = all_items;
+        {
+            let mut state = state.borrow_mut();
+            state.all_items = all_items;
+            state.all_enemies = all_enemies;
+        }
 
-        Self::new_compiled(assets, config, item_assets, engine, state, side_effects)
+        let mut model = Self::new_compiled(
+            config,
+            item_assets,
+            enemy_assets,
+            seed,
+            engine,
+            state,
+            side_effects,
+        );
+        model.next_level(true);
+        Ok(model)
     }
 
     fn new_compiled(
-        assets: Rc<Assets>,
         config: Config,
         item_assets: Rc<ItemAssets>,
+        enemy_assets: Rc<EnemyAssets>,
+        seed: u64,
         engine: Engine,
         state: Rc<RefCell<ModelState>>,
         side_effects: Rc<RefCell<Vec<Effect>>>,
     ) -> Self {
-        let mut model = Self {
-            assets,
+        let replay = Replay::new(seed, config.clone(), &state.borrow().all_items);
+        Self {
             config,
             item_assets,
+            enemy_assets,
+            seed,
             engine,
             state,
+            ticks: 0,
+            replay,
             level: 0,
             turn: 0,
             score: 0,
@@ -196,10 +274,11 @@ impl Model {
             resolved_items: Collection::new(),
 
             resolution_queue: VecDeque::new(),
+            chain_depth: 0,
+            enemy_queue: VecDeque::new(),
             effect_queue_stack: Vec::new(),
             side_effects,
-        };
-        model.next_level(true);
-        model
+            events: Vec::new(),
+        }
     }
 }
@@ -6,18 +6,31 @@ pub struct Player {
     pub hearts: usize,
     /// Extra items player can choose at the end of the turn.
     pub extra_items: usize,
-    /// Number of refreshes available in the select menu.
-    pub refreshes: usize,
+    pub gold: Gold,
     pub items: Arena<InventoryItem>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum PlayerInput {
     Dir(vec2<Coord>),
     Tile(vec2<Coord>),
-    Vision { pos: vec2<Coord>, commit: bool },
+    Vision {
+        pos: vec2<Coord>,
+        commit: bool,
+    },
     SelectItem(usize),
     Reroll,
+    /// Sell the item from the inventory while in the shop.
+    SellItem(Id),
+    /// Merge the copies of the inventory item into a higher tier while in the shop.
+    MergeItem(Id),
+    /// Drop the item from the inventory (and the board).
+    DiscardItem(Id),
+    /// Put the inventory item on the board, or move it if it is already there.
+    PlaceItem {
+        item: Id,
+        position: vec2<Coord>,
+    },
     Skip,
     Retry,
 }
@@ -29,7 +42,7 @@ impl Player {
             turns_left: 0,
             hearts: 3,
             extra_items: 0,
-            refreshes: 0,
+            gold: 0,
             items: Arena::new(),
         }
     }
@@ -0,0 +1,98 @@
+use super::*;
+
+/// A recording of a run, that can be played back through the model.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub config: Config,
+    /// Names of all items available in the run.
+    pub items: Vec<String>,
+    /// The time step of a single model update.
+    pub delta_time: Time,
+    pub inputs: Vec<ReplayInput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayInput {
+    /// The number of model updates performed before the input.
+    pub tick: usize,
+    pub input: PlayerInput,
+}
+
+/// Feeds the recorded inputs back into the model.
+pub struct ReplayPlayback {
+    pub replay: Replay,
+    next_input: usize,
+}
+
+impl Replay {
+    pub fn new(seed: u64, config: Config, items: &[ItemKind]) -> Self {
+        Self {
+            seed,
+            config,
+            items: items
+                .iter()
+                .map(|kind| kind.config.name.to_string())
+                .collect(),
+            delta_time: r32(FIXED_DELTA_TIME),
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .context(format!("when loading replay file at {:?}", path))?;
+        ron::from_str(&content).context(format!("when parsing replay file at {:?}", path))
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let content = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        std::fs::write(path, content).context(format!("when writing replay file at {:?}", path))?;
+        Ok(())
+    }
+
+    /// Warn if the items of the model differ from the recorded ones,
+    /// in which case the playback will most likely diverge.
+    pub fn check_items(&self, model: &Model) {
+        let items: Vec<String> = model
+            .state
+            .borrow()
+            .all_items
+            .iter()
+            .map(|kind| kind.config.name.to_string())
+            .collect();
+        if items != self.items {
+            log::warn!(
+                "The items differ from the ones in the replay, playback might diverge. Recorded: {:?}, current: {:?}",
+                self.items,
+                items
+            );
+        }
+    }
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next_input: 0,
+        }
+    }
+
+    /// Returns the next input that has to be applied before the update number `tick`.
+    pub fn next_input(&mut self, tick: usize) -> Option<PlayerInput> {
+        let input = self.replay.inputs.get(self.next_input)?;
+        if input.tick > tick {
+            return None;
+        }
+        self.next_input += 1;
+        Some(input.input.clone())
+    }
+
+    /// Whether all recorded inputs have been played back.
+    pub fn is_finished(&self) -> bool {
+        self.next_input >= self.replay.inputs.len()
+    }
+}
@@ -0,0 +1,282 @@
+use super::*;
+
+/// A snapshot of a run taken at the start of the player's turn.
+/// Animations and effect queues are not saved, since they are always empty at that point.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedRun {
+    /// The seed the run was started with.
+    pub seed: u64,
+    /// The seed to continue the random number generator from.
+    pub rng_seed: u64,
+    pub config: Config,
+    pub level: usize,
+    pub turn: usize,
+    pub score: Score,
+    pub grid: SavedGrid,
+    pub player: SavedPlayer,
+    pub entities: Vec<SavedEntity>,
+    pub visible_tiles: Vec<vec2<Coord>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedGrid {
+    pub tiles: Vec<vec2<Coord>>,
+    pub fractured: Vec<vec2<Coord>>,
+    pub lights: Vec<(vec2<Coord>, usize)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPlayer {
+    pub moves_left: usize,
+    pub turns_left: usize,
+    pub hearts: usize,
+    pub extra_items: usize,
+    #[serde(default)]
+    pub gold: Gold,
+    pub items: Vec<SavedItem>,
+}
+
+/// An inventory item. Its script is initialized anew when loaded, and then the memory is restored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedItem {
+    /// Name of the item kind.
+    pub kind: String,
+    pub turns_on_board: usize,
+    #[serde(default = "default_tier")]
+    pub tier: usize,
+    pub base_stats: ItemStats,
+    pub perm_stats: ItemStats,
+    pub temp_stats: ItemStats,
+    #[serde(default)]
+    pub memory: ItemMemory,
+    /// The item on the board, if it is present there.
+    pub board: Option<SavedBoardItem>,
+}
+
+fn default_tier() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedBoardItem {
+    pub position: vec2<Coord>,
+    pub used: bool,
+    #[serde(default)]
+    pub anchored: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedEntity {
+    pub position: vec2<Coord>,
+    pub fraction: Fraction,
+    pub health: Hp,
+    pub max_health: Hp,
+    #[serde(default)]
+    pub shield: Hp,
+    pub look_dir: vec2<Coord>,
+    pub kind: EntityKind,
+}
+
+impl Model {
+    /// Take a snapshot of the run, that can be continued with [Model::load].
+    ///
+    /// *NOTE*: the rng is reseeded, so that this run continues exactly like the loaded one would.
+    pub fn save_run(&mut self) -> SavedRun {
+        let state = self.state.borrow();
+        let rng_seed = state.rng.borrow_mut().gen();
+        *state.rng.borrow_mut() = StdRng::seed_from_u64(rng_seed);
+
+        let sorted = |positions: &HashSet<vec2<Coord>>| -> Vec<vec2<Coord>> {
+            positions
+                .iter()
+                .copied()
+                .sorted_by_key(|pos| (pos.x, pos.y))
+                .collect()
+        };
+
+        let grid = SavedGrid {
+            tiles: sorted(&state.grid.tiles),
+            fractured: sorted(&state.grid.fractured),
+            lights: state
+                .grid
+                .lights
+                .iter()
+                .map(|(&pos, &duration)| (pos, duration))
+                .sorted_by_key(|(pos, _)| (pos.x, pos.y))
+                .collect(),
+        };
+
+        let items = state
+            .player
+            .items
+            .iter()
+            .map(|(_, item)| SavedItem {
+                kind: item.kind.config.name.to_string(),
+                turns_on_board: item.turns_on_board,
+                tier: item.tier,
+                base_stats: item.base_stats.clone(),
+                perm_stats: item.perm_stats.clone(),
+                temp_stats: item.temp_stats.clone(),
+                memory: item.memory.clone(),
+                board: item
+                    .on_board
+                    .and_then(|id| state.items.get(id))
+                    .map(|board_item| SavedBoardItem {
+                        position: board_item.position,
+                        used: board_item.used,
+                        anchored: board_item.anchored,
+                    }),
+            })
+            .collect();
+        let player = SavedPlayer {
+            moves_left: state.player.moves_left,
+            turns_left: state.player.turns_left,
+            hearts: state.player.hearts,
+            extra_items: state.player.extra_items,
+            gold: state.player.gold,
+            items,
+        };
+
+        let entities = state
+            .entities
+            .iter()
+            .map(|(_, entity)| SavedEntity {
+                position: entity.position,
+                fraction: entity.fraction,
+                health: entity.health.value(),
+                max_health: entity.health.max(),
+                shield: entity.shield,
+                look_dir: entity.look_dir,
+                kind: entity.kind.clone(),
+            })
+            .collect();
+
+        SavedRun {
+            seed: self.seed,
+            rng_seed,
+            config: self.config.clone(),
+            level: self.level,
+            turn: self.turn,
+            score: self.score,
+            grid,
+            player,
+            entities,
+            visible_tiles: sorted(&state.visible_tiles),
+        }
+    }
+
+    /// Continue the saved run from the start of the player's turn.
+    pub fn load(
+        item_assets: Rc<ItemAssets>,
+        enemy_assets: Rc<EnemyAssets>,
+        saved: SavedRun,
+    ) -> anyhow::Result<Self> {
+        log::info!(
+            "Continuing the run with seed {} from level {}, turn {}",
+            saved.seed,
+            saved.level,
+            saved.turn
+        );
+        let state = ModelState {
+            all_items: vec![], // Initialized after engine
+            all_enemies: vec![],
+            grid: Grid {
+                tiles: saved.grid.tiles.into_iter().collect(),
+                fractured: saved.grid.fractured.into_iter().collect(),
+                lights: saved.grid.lights.into_iter().collect(),
+            },
+            player: Player {
+                moves_left: saved.player.moves_left,
+                turns_left: saved.player.turns_left,
+                hearts: saved.player.hearts,
+                extra_items: saved.player.extra_items,
+                gold: saved.player.gold,
+                items: Arena::new(),
+            },
+            items: Arena::new(),
+            entities: saved
+                .entities
+                .into_iter()
+                .map(|entity| {
+                    let mut health = Health::new_max(entity.max_health);
+                    health.change(entity.health - entity.max_health);
+                    Entity {
+                        position: entity.position,
+                        fraction: entity.fraction,
+                        health,
+                        shield: entity.shield,
+                        look_dir: entity.look_dir,
+                        kind: entity.kind,
+                    }
+                })
+                .collect(),
+            visible_tiles: saved.visible_tiles.into_iter().collect(),
+            rng: RefCell::new(StdRng::seed_from_u64(saved.rng_seed)),
+        };
+        let state = Rc::new(RefCell::new(state));
+        let side_effects = Rc::new(RefCell::new(Vec::new()));
+
+        let engine = Engine::new(
+            Rc::clone(&state),
+            Rc::clone(&side_effects),
+            saved.config.script_budget,
+        )
+        .context("when initializing the script engine")?;
+        let all_items = engine
+            .compile_items(&item_assets, saved.config.strict_scripts)
+            .context("when compiling items")?;
+        let all_enemies = engine
+            .compile_enemies(&enemy_assets, saved.config.strict_scripts)
+            .context("when compiling enemies")?;
+
+        {
+            // What is this trick KEKW
+            let mut state = state.borrow_mut();
+            let state = &mut *state;
+
+            for saved_item in saved.player.items {
+                let kind = all_items
+                    .iter()
+                    .find(|kind| *kind.config.name == *saved_item.kind)
+                    .with_context(|| format!("unknown item {}", saved_item.kind))?;
+                let mut item = engine
+                    .init_item(kind.clone())
+                    .with_context(|| format!("when initializing item {}", saved_item.kind))?;
+                item.turns_on_board = saved_item.turns_on_board;
+                item.tier = saved_item.tier;
+                item.base_stats = saved_item.base_stats;
+                item.perm_stats = saved_item.perm_stats;
+                item.temp_stats = saved_item.temp_stats;
+                item.memory = saved_item.memory;
+
+                let item_id = state.player.items.insert(item);
+                if let Some(board_item) = saved_item.board {
+                    let on_board = state.items.insert(BoardItem {
+                        position: board_item.position,
+                        item_id,
+                        used: board_item.used,
+                        anchored: board_item.anchored,
+                    });
+                    state.player.items[item_id].on_board = Some(on_board);
+                }
+            }
+            state.all_items = all_items;
+            state.all_enemies = all_enemies;
+        }
+
+        let mut model = Self::new_compiled(
+            saved.config,
+            item_assets,
+            enemy_assets,
+            saved.seed,
+            engine,
+            state,
+            side_effects,
+        );
+        model.level = saved.level;
+        model.turn = saved.turn;
+        model.score = saved.score;
+        model.phase = Phase::Player;
+        Ok(model)
+    }
+}
@@ -0,0 +1,136 @@
+use super::*;
+
+use std::ops::Range;
+
+/// Script functions that take the name of an item as the argument.
+const NAME_REFERENCES: [&str; 2] = ["turn_into", "Filter::Named"];
+
+/// Enemy script functions that take the name of an enemy as the argument.
+const ENEMY_NAME_REFERENCES: [&str; 1] = ["spawn_minion"];
+
+/// Cross-check the item names referenced in the config and in the scripts,
+/// and collect the problems found when loading the items.
+/// Does not compile the scripts, see [check_items] for that.
+pub fn validate_items(config: &Config, all_items: &ItemAssets) -> ItemReport {
+    let mut report = ItemReport {
+        diagnostics: all_items.problems.clone(),
+    };
+
+    for name in &config.starting_items {
+        if !all_items.assets.contains_key(&**name) {
+            report.diagnostics.push(ItemDiagnostic::error(
+                "config",
+                format!("unknown starting item {:?}", name),
+            ));
+        }
+    }
+
+    for (name, item) in all_items.assets.iter().sorted_by(|(a, _), (b, _)| a.cmp(b)) {
+        let shop = &item.config.shop;
+        if !shop.weight.is_finite() || shop.weight < 0.0 {
+            report.diagnostics.push(ItemDiagnostic::error(
+                Rc::clone(name),
+                format!(
+                    "the shop weight must be a non-negative number, got {}",
+                    shop.weight
+                ),
+            ));
+        }
+        if let Some(max_level) = shop
+            .max_level
+            .filter(|&max_level| max_level < shop.min_level)
+        {
+            report.diagnostics.push(ItemDiagnostic::error(
+                Rc::clone(name),
+                format!(
+                    "the item is never offered: min_level {} is above max_level {}",
+                    shop.min_level, max_level
+                ),
+            ));
+        }
+
+        for required in &shop.requires {
+            if !all_items.assets.contains_key(required) {
+                report.diagnostics.push(ItemDiagnostic::error(
+                    Rc::clone(name),
+                    format!("unknown required item {:?}", required),
+                ));
+            }
+        }
+
+        let Some(script) = &item.script else {
+            continue;
+        };
+        for (span, target) in name_references(script, &NAME_REFERENCES) {
+            if !all_items.assets.contains_key(target) {
+                report.diagnostics.push(ItemDiagnostic::new(
+                    Rc::clone(name),
+                    Severity::Error,
+                    script,
+                    Some(span),
+                    format!("unknown item {:?}", target),
+                ));
+            }
+        }
+    }
+
+    report
+}
+
+/// Check the enemy names referenced in the enemy scripts,
+/// and collect the problems found when loading the enemies.
+pub fn validate_enemies(all_enemies: &EnemyAssets) -> ItemReport {
+    let mut report = ItemReport {
+        diagnostics: all_enemies.problems.clone(),
+    };
+
+    for (name, enemy) in all_enemies
+        .assets
+        .iter()
+        .sorted_by(|(a, _), (b, _)| a.cmp(b))
+    {
+        let Some(script) = &enemy.script else {
+            continue;
+        };
+        for (span, target) in name_references(script, &ENEMY_NAME_REFERENCES) {
+            if !all_enemies.assets.contains_key(target) {
+                report.diagnostics.push(ItemDiagnostic::new(
+                    Rc::clone(name),
+                    Severity::Error,
+                    script,
+                    Some(span),
+                    format!("unknown enemy {:?}", target),
+                ));
+            }
+        }
+    }
+
+    report
+}
+
+/// Find the string literals passed to the functions expecting a name.
+/// Returns the span of the literal and the name.
+fn name_references<'a>(script: &'a str, functions: &[&str]) -> Vec<(Range<usize>, &'a str)> {
+    let mut references = Vec::new();
+    for &function in functions {
+        for (start, _) in script.match_indices(function) {
+            let args_start = start + function.len();
+            let args = &script[args_start..];
+            let Some(args) = args.trim_start().strip_prefix('(') else {
+                continue;
+            };
+            let Some(literal) = args.trim_start().strip_prefix('"') else {
+                // Not a literal, cannot check
+                continue;
+            };
+            let Some(end) = literal.find('"') else {
+                continue;
+            };
+            let literal_start = script.len() - literal.len() - 1;
+            let span = literal_start..literal_start + end + 2;
+            references.push((span, &literal[..end]));
+        }
+    }
+    references.sort_by_key(|(span, _)| span.start);
+    references
+}
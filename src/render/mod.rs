@@ -4,18 +4,30 @@ pub struct GameRender {
     geng: Geng,
     assets: Rc<Assets>,
     items: Rc<ItemAssets>,
+    enemies: Rc<EnemyAssets>,
     pub hide_item_hint: bool,
     pub portrait: bool,
     pub ui_camera: Camera2d,
     pub world_camera: Camera2d,
     pub cell_size: vec2<f32>,
     pub buttons: Vec<(ItemKind, Aabb2<f32>)>,
+    /// The items shown in the inventory, can be clicked to sell them in the shop.
+    pub inventory_slots: Vec<(Id, Aabb2<f32>)>,
+    /// The inventory item being dragged onto the board during the placement.
+    pub dragged_item: Option<Id>,
     pub skip_turn_button: Aabb2<f32>,
     pub skip_item_button: Aabb2<f32>,
     pub reroll_button: Aabb2<f32>,
     pub inventory_button: Aabb2<f32>,
     pub show_inventory: bool,
     pub retry_button: Aabb2<f32>,
+    /// Messages for the player that disappear after some time.
+    pub toasts: Vec<Toast>,
+}
+
+pub struct Toast {
+    pub text: String,
+    pub lifetime: Lifetime,
 }
 
 #[derive(Debug)]
@@ -26,11 +38,17 @@ enum TileLight {
 }
 
 impl GameRender {
-    pub fn new(geng: &Geng, assets: &Rc<Assets>, items: &Rc<ItemAssets>) -> Self {
+    pub fn new(
+        geng: &Geng,
+        assets: &Rc<Assets>,
+        items: &Rc<ItemAssets>,
+        enemies: &Rc<EnemyAssets>,
+    ) -> Self {
         Self {
             geng: geng.clone(),
             assets: assets.clone(),
             items: items.clone(),
+            enemies: enemies.clone(),
             hide_item_hint: false,
             portrait: false,
             ui_camera: Camera2d {
@@ -45,6 +63,8 @@ impl GameRender {
             },
             cell_size: vec2(1.0, 1.0),
             buttons: Vec::new(),
+            inventory_slots: Vec::new(),
+            dragged_item: None,
             skip_turn_button: Aabb2::point(vec2(7.0, -1.0))
                 .extend_symmetric(vec2::splat(1.5) / 2.0),
             skip_item_button: Aabb2::point(vec2(0.75, -3.0))
@@ -53,9 +73,35 @@ impl GameRender {
             inventory_button: Aabb2::point(vec2(7.0, 1.0)).extend_symmetric(vec2::splat(1.5) / 2.0),
             retry_button: Aabb2::point(vec2(0.0, -3.0)).extend_symmetric(vec2::splat(1.5) / 2.0),
             show_inventory: false,
+            toasts: Vec::new(),
         }
     }
 
+    /// Replace the item assets, e.g. after some have been reloaded.
+    pub fn set_items(&mut self, items: Rc<ItemAssets>) {
+        self.items = items;
+    }
+
+    /// Show a message for a few seconds.
+    pub fn show_toast(&mut self, text: impl Into<String>) {
+        // Keep only the latest ones
+        let max_toasts = 3;
+        if self.toasts.len() >= max_toasts {
+            self.toasts.remove(0);
+        }
+        self.toasts.push(Toast {
+            text: text.into(),
+            lifetime: Lifetime::new_max(r32(5.0)),
+        });
+    }
+
+    pub fn update(&mut self, delta_time: Time) {
+        for toast in &mut self.toasts {
+            toast.lifetime.change(-delta_time);
+        }
+        self.toasts.retain(|toast| toast.lifetime.is_above_min());
+    }
+
     pub fn draw(
         &mut self,
         model: &Model,
@@ -92,6 +138,7 @@ impl GameRender {
 
         if let Phase::GameOver = model.phase {
             self.draw_game_over(model, cursor_ui_pos, framebuffer);
+            self.draw_toasts(framebuffer);
             return;
         }
 
@@ -147,7 +194,7 @@ impl GameRender {
                         if state.items.iter().any(|(_, item)| {
                             item.position == pos
                                 && ItemFilter::Category(Category::Magic)
-                                    .check(&model.state.borrow().player.items[item.item_id].kind)
+                                    .check(&model.state.borrow().player.items[item.item_id])
                         }) {
                             TileLight::Light
                         } else {
@@ -265,6 +312,18 @@ impl GameRender {
                     * mat3::translate(vec2(0.0, -0.25)),
                 Color::try_from("#7a7a7a").unwrap(),
             );
+
+            // Gold
+            self.assets.font.draw(
+                framebuffer,
+                &self.ui_camera,
+                &format!("GOLD {}", model.state.borrow().player.gold),
+                vec2(geng::TextAlign::RIGHT, geng::TextAlign::CENTER),
+                mat3::translate(pos - vec2(0.0, height * 1.2))
+                    * mat3::scale_uniform(height)
+                    * mat3::translate(vec2(0.0, -0.25)),
+                Color::try_from("#e3a912").unwrap(),
+            );
         }
 
         // Overlay
@@ -319,6 +378,11 @@ impl GameRender {
 
                 "Select a position to place a new tile"
             }
+            Phase::Discard { .. } => "Inventory is full, discard an item",
+            Phase::Placement { .. } => {
+                skip = true;
+                "Drag the items onto the board"
+            }
             Phase::Select { .. } => {
                 if !self.show_inventory {
                     // Darken the game
@@ -370,8 +434,10 @@ impl GameRender {
             );
         }
 
-        if self.show_inventory {
-            self.draw_inventory(model, cursor_ui_pos, framebuffer);
+        if self.show_inventory || matches!(model.phase, Phase::Discard { .. }) {
+            self.inventory_slots = self.draw_inventory(model, cursor_ui_pos, framebuffer);
+        } else if let Phase::Placement { .. } = model.phase {
+            self.inventory_slots = self.draw_unplaced_items(model, cursor_ui_pos, framebuffer);
         } else if let Phase::Select { options, .. } = &model.phase {
             // Buttons
             let size = 2.0;
@@ -386,6 +452,7 @@ impl GameRender {
                 })
                 .collect();
 
+            let gold = model.state.borrow().player.gold;
             let mut hint = None;
             for (item, target) in &self.buttons {
                 // TODO: default texture
@@ -401,16 +468,19 @@ impl GameRender {
                 };
                 self.draw_at_ui(*target, background, framebuffer);
                 self.draw_at_ui(*target, texture, framebuffer);
+                self.draw_price(item.config.shop.price, gold, *target, framebuffer);
             }
 
-            if model.state.borrow().player.refreshes > 0 {
+            let reroll_price = model.config.economy.reroll_price;
+            if gold >= reroll_price {
                 self.draw_button(
                     self.reroll_button,
                     &self.assets.sprites.reroll_button,
                     cursor_ui_pos,
                     framebuffer,
-                )
-            };
+                );
+                self.draw_price(reroll_price, gold, self.reroll_button, framebuffer);
+            }
             self.draw_button(
                 self.skip_item_button,
                 &self.assets.sprites.skip_button,
@@ -461,6 +531,37 @@ impl GameRender {
             &self.ui_camera,
             &draw2d::Quad::new(overlay, color),
         );
+
+        self.draw_toasts(framebuffer);
+    }
+
+    fn draw_toasts(&self, framebuffer: &mut ugli::Framebuffer) {
+        let height = 0.4;
+        let width = self.ui_camera.fov * 0.8;
+        let bottom = self.ui_camera.center.y - self.ui_camera.fov / 2.0 + height;
+        for (i, toast) in self.toasts.iter().rev().enumerate() {
+            // Fade out during the last second
+            let alpha = toast.lifetime.value().as_f32().min(1.0);
+            let pos = vec2(self.ui_camera.center.x, bottom + i as f32 * height * 1.2);
+            let target = Aabb2::point(pos).extend_symmetric(vec2(width, height) / 2.0);
+
+            let mut color = Color::BLACK;
+            color.a = 0.7 * alpha;
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.ui_camera,
+                &draw2d::Quad::new(target, color),
+            );
+
+            let mut color = Color::try_from("#c9464b").unwrap();
+            color.a = alpha;
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.ui_camera,
+                &draw2d::Text::unit(self.assets.font.clone(), &toast.text, color)
+                    .fit_into(target.extend_uniform(-height * 0.15)),
+            );
+        }
     }
 
     fn draw_game_over(
@@ -688,7 +789,7 @@ impl GameRender {
                         &self.world_camera,
                         &draw2d::Text::unit(
                             self.assets.font.clone(),
-                            format!("{:+}", bonus.damage.unwrap_or_default()),
+                            bonus_text(bonus),
                             Color::try_from("#424242").unwrap(),
                         )
                         .fit_into(target),
@@ -699,12 +800,13 @@ impl GameRender {
         }
     }
 
+    /// Returns the slots of the items drawn.
     fn draw_inventory(
         &self,
         model: &Model,
         cursor_ui_pos: vec2<f32>,
         framebuffer: &mut ugli::Framebuffer,
-    ) {
+    ) -> Vec<(Id, Aabb2<f32>)> {
         // Darken the game
         let size = vec2(16.0 / 9.0, 1.0) * self.ui_camera.fov;
         let overlay = Aabb2::point(self.ui_camera.center).extend_symmetric(size / 2.0);
@@ -728,15 +830,37 @@ impl GameRender {
         }
         items.sort_by_key(|&(i, _)| i); // TODO: sort by age
 
+        let selling = matches!(model.phase, Phase::Select { .. });
+        let discarding = matches!(model.phase, Phase::Player | Phase::Discard { .. });
+
+        // Capacity
+        let capacity = model.config.inventory_capacity;
+        let color = if items.len() > capacity {
+            "#c03d43"
+        } else {
+            "#ffe7cd"
+        };
+        self.geng.draw2d().draw2d(
+            framebuffer,
+            &self.ui_camera,
+            &draw2d::Text::unit(
+                self.assets.font.clone(),
+                format!("{}/{}", items.len(), capacity),
+                Color::try_from(color).unwrap(),
+            )
+            .fit_into(Aabb2::point(vec2(0.0, 3.1)).extend_symmetric(vec2(2.0, 0.3) / 2.0)),
+        );
         let size = vec2(1.5, 1.5);
         let mut hint = None;
+        let mut slots = Vec::with_capacity(items.len());
         let row_max = if self.portrait { 3 } else { 5 };
         let offset = items.len().min(row_max).saturating_sub(1) as f32 / 2.0;
-        for (i, (_, item)) in items.iter().enumerate() {
+        for (i, &(id, item)) in items.iter().enumerate() {
             let x = i % row_max;
             let y = i / row_max;
             let pos = vec2(0.0, 2.0) + vec2(x as f32 - offset, -(y as f32)) * size;
             let target = Aabb2::point(pos).extend_symmetric(size / 2.0);
+            slots.push((id, target));
 
             if target.contains(cursor_ui_pos) {
                 hint = Some(item);
@@ -748,6 +872,34 @@ impl GameRender {
                 .get_texture(&item.kind.config.name)
                 .unwrap_or(&self.assets.sprites.item_shadow);
             self.draw_at_ui(target, texture, framebuffer);
+            self.draw_tier(
+                item.tier,
+                pos + vec2(0.0, 0.4) * size,
+                0.1 * size.y,
+                Color::try_from("#e3a912").unwrap(),
+                &self.ui_camera,
+                framebuffer,
+            );
+            if selling {
+                let text = if model.merge_copies(&state, id).is_some() {
+                    format!("SELL {} / MERGE", item.kind.config.shop.sell_price())
+                } else {
+                    format!("SELL {}", item.kind.config.shop.sell_price())
+                };
+                self.draw_text_below(
+                    &text,
+                    Color::try_from("#e3a912").unwrap(),
+                    target,
+                    framebuffer,
+                );
+            } else if discarding {
+                self.draw_text_below(
+                    "DISCARD",
+                    Color::try_from("#c03d43").unwrap(),
+                    target,
+                    framebuffer,
+                );
+            }
 
             // if count > 1 {
             //     let pos = pos + vec2(0.3, 0.3) * size;
@@ -781,6 +933,123 @@ impl GameRender {
                 framebuffer,
             );
         }
+
+        slots
+    }
+
+    /// Draw the items that are not on the board in a row at the bottom, and the dragged item.
+    /// Returns the slots of the items drawn.
+    fn draw_unplaced_items(
+        &self,
+        model: &Model,
+        cursor_ui_pos: vec2<f32>,
+        framebuffer: &mut ugli::Framebuffer,
+    ) -> Vec<(Id, Aabb2<f32>)> {
+        let state = model.state.borrow();
+        let items: Vec<(Id, &InventoryItem)> = state
+            .player
+            .items
+            .iter()
+            .filter(|(_, item)| item.on_board.is_none())
+            .sorted_by_key(|&(id, _)| id)
+            .collect();
+
+        let size = vec2(1.2, 1.2);
+        let offset = items.len().saturating_sub(1) as f32 / 2.0;
+        let mut slots = Vec::with_capacity(items.len());
+        for (i, &(id, item)) in items.iter().enumerate() {
+            let pos = vec2(0.0, -3.5) + vec2(i as f32 - offset, 0.0) * size;
+            let target = Aabb2::point(pos).extend_symmetric(size / 2.0 * 0.9);
+            slots.push((id, target));
+
+            let background = if target.contains(cursor_ui_pos) {
+                &self.assets.sprites.cell
+            } else {
+                &self.assets.sprites.cell_dark
+            };
+            self.draw_at_ui(target, background, framebuffer);
+            if self.dragged_item != Some(id) {
+                let texture = self
+                    .items
+                    .get_texture(&item.kind.config.name)
+                    .unwrap_or(&self.assets.sprites.item_shadow);
+                self.draw_at_ui(target, texture, framebuffer);
+            }
+        }
+
+        let dragged = self.dragged_item.and_then(|id| state.player.items.get(id));
+        if let Some(item) = dragged {
+            let texture = self
+                .items
+                .get_texture(&item.kind.config.name)
+                .unwrap_or(&self.assets.sprites.item_shadow);
+            let target = Aabb2::point(cursor_ui_pos).extend_symmetric(size / 2.0);
+            self.draw_at_ui(target, texture, framebuffer);
+        }
+
+        slots
+    }
+
+    /// Draw a star for every tier above the first, in a row centered at the position.
+    fn draw_tier(
+        &self,
+        tier: usize,
+        pos: vec2<f32>,
+        radius: f32,
+        color: Color,
+        camera: &Camera2d,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let stars = tier.saturating_sub(1);
+        let offset = (stars as f32 - 1.0) / 2.0;
+        for i in 0..stars {
+            let center = pos + vec2((i as f32 - offset) * radius * 2.2, 0.0);
+            // Fan around the center, alternating the outer and the inner points
+            let vertices = std::iter::once(center)
+                .chain((0..=10).map(|j| {
+                    let r = if j % 2 == 0 { radius } else { radius * 0.45 };
+                    let angle = Angle::from_degrees(90.0 + j as f32 * 36.0);
+                    center + angle.unit_vec() * r
+                }))
+                .collect();
+            self.geng
+                .draw2d()
+                .draw2d(framebuffer, camera, &draw2d::Polygon::new(vertices, color));
+        }
+    }
+
+    /// Draw the price under the button, in red if the player cannot afford it.
+    fn draw_price(
+        &self,
+        price: Gold,
+        gold: Gold,
+        button: Aabb2<f32>,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let color = if gold >= price { "#e3a912" } else { "#c03d43" };
+        self.draw_text_below(
+            &format!("{} GOLD", price),
+            Color::try_from(color).unwrap(),
+            button,
+            framebuffer,
+        );
+    }
+
+    fn draw_text_below(
+        &self,
+        text: &str,
+        color: Color,
+        target: Aabb2<f32>,
+        framebuffer: &mut ugli::Framebuffer,
+    ) {
+        let height = 0.25;
+        let pos = vec2(target.center().x, target.min.y - height / 2.0);
+        self.geng.draw2d().draw2d(
+            framebuffer,
+            &self.ui_camera,
+            &draw2d::Text::unit(self.assets.font.clone(), text, color)
+                .fit_into(Aabb2::point(pos).extend_symmetric(vec2(target.width(), height) / 2.0)),
+        );
     }
 
     fn draw_item_hint(
@@ -900,12 +1169,35 @@ impl GameRender {
         let description = self
             .items
             .get(&item.config.name)
-            .description
-            .as_deref()
+            .and_then(|item| item.description.as_deref())
             .unwrap_or("<description missing>");
         let stats = stats.unwrap_or(&item.config.base_stats);
-        let description =
-            description.replace("{damage}", &format!("{}", stats.damage.unwrap_or_default()));
+        let mut description = description.to_string();
+        for stat in Stat::ALL {
+            let value = stats.get(stat).unwrap_or_default();
+            description = description.replace(&format!("{{{}}}", stat.name()), &value.to_string());
+        }
+
+        let font_size = target.height() * 0.03;
+
+        // Stats
+        let stats_line = stats
+            .iter()
+            .map(|(stat, value)| format!("{} {}", stat.name(), value))
+            .join("  ");
+        if !stats_line.is_empty() {
+            self.assets.font.draw(
+                framebuffer,
+                &self.ui_camera,
+                &stats_line,
+                vec2::splat(geng::TextAlign::LEFT),
+                mat3::translate(desc_target.top_left())
+                    * mat3::scale_uniform(font_size)
+                    * mat3::translate(vec2(0.0, -0.25)),
+                Color::try_from("#c9464b").unwrap(),
+            );
+            desc_target.max.y -= font_size * 1.5;
+        }
 
         let mut lines = Vec::new();
         for source_line in description.lines() {
@@ -928,7 +1220,6 @@ impl GameRender {
             }
         }
 
-        let font_size = target.height() * 0.03;
         for (i, line) in lines.into_iter().enumerate() {
             let position = desc_target.top_left() - vec2(0.0, i as f32) * font_size * 1.2;
             self.assets.font.draw(
@@ -1020,6 +1311,70 @@ impl GameRender {
                     .fit_into(target),
             );
         }
+
+        // Other stats
+        let stats = item
+            .current_stats()
+            .iter()
+            .filter(|(stat, _)| *stat != Stat::Damage)
+            .map(|(stat, value)| format!("{}{}", value, stat.short_name()))
+            .join(" ");
+        if !stats.is_empty() {
+            let pos = (position + vec2(0.0, -0.4)) * self.cell_size;
+            let target = Aabb2::point(pos).extend_symmetric(vec2(0.4, 0.06));
+            let mut color = Color::try_from("#ffe7cd").unwrap();
+            color.a = alpha;
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.world_camera,
+                &draw2d::Text::unit(self.assets.font.clone(), stats, color).fit_into(target),
+            );
+        }
+
+        // Tier
+        let mut tier_color = Color::try_from("#e3a912").unwrap();
+        tier_color.a = alpha;
+        self.draw_tier(
+            item.tier,
+            (position + offset + vec2(0.0, 0.42)) * self.cell_size,
+            0.05,
+            tier_color,
+            &self.world_camera,
+            framebuffer,
+        );
+
+        // Anchor
+        if board_item.is_anchored(&state) {
+            let pos = (position + offset + vec2(-0.3, -0.3)) * self.cell_size;
+            let mut color = Color::try_from("#6467b6").unwrap();
+            color.a = alpha;
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.world_camera,
+                &draw2d::Ellipse::circle_with_cut(pos, 0.03, 0.06, color),
+            );
+        }
+
+        // Counter from the memory
+        let counter = item
+            .kind
+            .config
+            .memory
+            .counter
+            .as_ref()
+            .and_then(|key| item.memory.get(key));
+        if let Some(value) = counter {
+            let pos = (position + vec2(-0.3, 0.3)) * self.cell_size;
+            let target = Aabb2::point(pos).extend_uniform(0.06);
+            let mut color = Color::try_from("#ffe7cd").unwrap();
+            color.a = alpha;
+            self.geng.draw2d().draw2d(
+                framebuffer,
+                &self.world_camera,
+                &draw2d::Text::unit(self.assets.font.clone(), format!("{}", value), color)
+                    .fit_into(target),
+            );
+        }
     }
 
     fn draw_at_grid(
@@ -1156,7 +1511,29 @@ impl GameRender {
                     .fit_into(target),
                 );
 
-                &self.assets.sprites.enemy
+                if entity.shield > 0 {
+                    let pos = (position + vec2(-0.3, 0.3)) * self.cell_size;
+                    let mut color = Color::try_from("#6467b6").unwrap();
+                    color.a = alpha;
+                    self.geng.draw2d().draw2d(
+                        framebuffer,
+                        &self.world_camera,
+                        &draw2d::Text::unit(
+                            self.assets.font.clone(),
+                            format!("+{}", entity.shield),
+                            color,
+                        )
+                        .fit_into(Aabb2::point(pos).extend_uniform(0.08)),
+                    );
+                }
+
+                match &entity.kind {
+                    EntityKind::Enemy(name) => self
+                        .enemies
+                        .get_texture(name)
+                        .unwrap_or(&self.assets.sprites.enemy),
+                    EntityKind::Player => &self.assets.sprites.enemy,
+                }
             }
         };
 
@@ -1220,3 +1597,14 @@ impl GameRender {
         )
     }
 }
+
+/// The text shown on a bonus flying to an item, e.g. `+2` for damage or `+1rng` for other stats.
+fn bonus_text(bonus: &ItemStats) -> String {
+    bonus
+        .iter()
+        .map(|(stat, value)| match stat {
+            Stat::Damage => format!("{:+}", value),
+            _ => format!("{:+}{}", value, stat.short_name()),
+        })
+        .join(" ")
+}
@@ -0,0 +1,101 @@
+use crate::prelude::*;
+
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// How often to check the files, in seconds.
+const POLL_INTERVAL: f32 = 0.5;
+
+/// The files of an item that are reloaded when changed.
+const ITEM_FILES: [&str; 3] = ["config.ron", "description.txt", "script.rn"];
+
+/// The directory of the script library, all of its files are watched.
+const LIBRARY_DIR: &str = "_lib";
+
+/// Watches the item directories and the script library
+/// by polling the modification times of their files.
+pub struct ItemWatcher {
+    /// Path to the items directory, the one with `_list.ron`.
+    path: PathBuf,
+    /// The latest modification time of the files in every item directory.
+    stamps: HashMap<PathBuf, SystemTime>,
+    next_poll: f32,
+}
+
+impl ItemWatcher {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        let mut watcher = Self {
+            path: path.into(),
+            stamps: HashMap::new(),
+            next_poll: POLL_INTERVAL,
+        };
+        watcher.stamps = watcher.scan();
+        watcher
+    }
+
+    /// The directory of the script library, reported by [ItemWatcher::update] when it changes.
+    pub fn library_path(&self) -> PathBuf {
+        self.path.join(LIBRARY_DIR)
+    }
+
+    /// Returns the directories of the items (or the library) that have changed since the last check.
+    pub fn update(&mut self, delta_time: f32) -> Vec<PathBuf> {
+        self.next_poll -= delta_time;
+        if self.next_poll > 0.0 {
+            return Vec::new();
+        }
+        self.next_poll = POLL_INTERVAL;
+
+        let stamps = self.scan();
+        let changed = stamps
+            .iter()
+            .filter(|(dir, stamp)| self.stamps.get(*dir) != Some(stamp))
+            .map(|(dir, _)| dir.clone())
+            .sorted()
+            .collect();
+        self.stamps = stamps;
+        changed
+    }
+
+    /// Collect the modification times of the items in the list and of the library.
+    fn scan(&self) -> HashMap<PathBuf, SystemTime> {
+        let list_path = self.path.join("_list.ron");
+        let list: Vec<String> = match std::fs::read_to_string(&list_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|list| ron::from_str(&list).map_err(anyhow::Error::from))
+        {
+            Ok(list) => list,
+            Err(err) => {
+                log::error!("Failed to read the item list at {:?}: {:?}", list_path, err);
+                // Keep the old state, so that nothing is reloaded by accident
+                return self.stamps.clone();
+            }
+        };
+
+        let library = self.library_path();
+        let library_stamp = library_modified(&library).map(|stamp| (library, stamp));
+        list.into_iter()
+            .map(|name| self.path.join(name))
+            .filter_map(|dir| modified(&dir).map(|stamp| (dir, stamp)))
+            .chain(library_stamp)
+            .collect()
+    }
+}
+
+/// The latest modification time of the item files in the directory.
+fn modified(dir: &Path) -> Option<SystemTime> {
+    ITEM_FILES
+        .iter()
+        .filter_map(|file| std::fs::metadata(dir.join(file)).ok()?.modified().ok())
+        .max()
+}
+
+/// The latest modification time of any file in the library directory.
+fn library_modified(dir: &Path) -> Option<SystemTime> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+        .max()
+}